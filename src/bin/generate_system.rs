@@ -0,0 +1,61 @@
+use star_explorer_lib::system_generator::generate_system;
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: star_explorer-generate [SEED] [--out DIR]
+
+Prints the system generated from SEED as JSON, or writes it to
+DIR/<star>.system.json. Without a seed a random one is used and reported.";
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args
+        .iter()
+        .map(|a| a.as_str())
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["-h" | "--help"] => {
+            println!("{USAGE}");
+            ExitCode::SUCCESS
+        }
+        [seed, "--out", dir] => match seed.parse() {
+            Ok(seed) => generate(seed, Some(Path::new(dir))),
+            Err(_) => usage(),
+        },
+        ["--out", dir] => generate(rand::random(), Some(Path::new(dir))),
+        [seed] => match seed.parse() {
+            Ok(seed) => generate(seed, None),
+            Err(_) => usage(),
+        },
+        [] => generate(rand::random(), None),
+        _ => usage(),
+    }
+}
+
+fn usage() -> ExitCode {
+    eprintln!("{USAGE}");
+    ExitCode::FAILURE
+}
+
+fn generate(seed: u64, dir: Option<&Path>) -> ExitCode {
+    let system = generate_system(seed);
+    let json = serde_json::to_string_pretty(&system).expect("systems always serialize");
+    let Some(dir) = dir else {
+        eprintln!("seed {seed}");
+        println!("{json}");
+        return ExitCode::SUCCESS;
+    };
+    let path = dir.join(format!("{}.system.json", system.name.to_lowercase()));
+    match fs::create_dir_all(dir).and_then(|()| fs::write(&path, json + "\n")) {
+        Ok(()) => {
+            println!("seed {seed}: wrote {}", path.display());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}: {e}", path.display());
+            ExitCode::FAILURE
+        }
+    }
+}
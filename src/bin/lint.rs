@@ -0,0 +1,72 @@
+use star_explorer_lib::dialogue_actions::ActionRegistry;
+use star_explorer_lib::dialogue_graph::{GraphFormat, dialogue_graph};
+use star_explorer_lib::dialogue_lint::{lint_dialogue_dir, read_dialogue};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: star_explorer-lint [DIALOGUE_DIR]
+       star_explorer-lint graph [--format dot|mermaid] DIALOGUE_FILE";
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args
+        .iter()
+        .map(|a| a.as_str())
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["-h" | "--help"] => {
+            println!("{USAGE}");
+            ExitCode::SUCCESS
+        }
+        ["graph", "--format", format, file] => match GraphFormat::from_name(format) {
+            Some(format) => graph(Path::new(file), format),
+            None => usage(),
+        },
+        ["graph", file] => graph(Path::new(file), GraphFormat::Dot),
+        [dir] => lint(&PathBuf::from(dir)),
+        [] => lint(&PathBuf::from("assets/dialogue")),
+        _ => usage(),
+    }
+}
+
+fn usage() -> ExitCode {
+    eprintln!("{USAGE}");
+    ExitCode::FAILURE
+}
+
+fn lint(dir: &Path) -> ExitCode {
+    // Actions the game registers beyond the built-in ones are only warned about.
+    let issues = lint_dialogue_dir(dir, &ActionRegistry::default());
+    for issue in issues.iter() {
+        eprintln!("{issue}");
+    }
+    let errors = issues.iter().filter(|issue| issue.is_error()).count();
+    let warnings = issues.len() - errors;
+    if errors == 0 {
+        match warnings {
+            0 => println!("{}: no problems found", dir.display()),
+            _ => println!(
+                "{}: no problems found, {warnings} warning(s)",
+                dir.display()
+            ),
+        }
+        ExitCode::SUCCESS
+    } else {
+        eprintln!("{errors} problem(s) found, {warnings} warning(s)");
+        ExitCode::FAILURE
+    }
+}
+
+fn graph(file: &Path, format: GraphFormat) -> ExitCode {
+    match read_dialogue(file) {
+        Ok(dialogue) => {
+            print!("{}", dialogue_graph(&dialogue, format));
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}: {e}", file.display());
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -1,80 +1,671 @@
-use crate::story_system::{ActiveDialogue, GameFlags};
+use crate::GameActions;
+use crate::background_stars::BackgroundStarConfig;
+use crate::dialogue_template::{ShipStats, TemplateContext, render};
+use crate::input_actions::ActionState;
+use crate::localization::Localization;
+use crate::player_ship::MyShip;
+use crate::solar_system::SolarBody;
+use crate::space_position::SpacePosition;
+use crate::story_system::{ActiveDialogue, Choice, GameFlags, Speaker};
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
-use bevy::text::TextBounds;
+use bevy::ui::widget::NodeImageMode;
+use std::collections::HashSet;
+use std::ops::Range;
 
 pub struct CommunicationsSystemPlugin;
 
 impl Plugin for CommunicationsSystemPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, update_window);
+        app.init_resource::<TextReveal>();
+        app.init_resource::<ChoiceCursor>();
+        app.add_systems(Startup, spawn_window);
+        app.add_systems(
+            Update,
+            (
+                choose_choice,
+                reveal_text,
+                update_window,
+                update_speaker,
+                update_choices,
+            )
+                .chain(),
+        );
     }
 }
 
-fn update_window(
-    mut cmd: Commands,
-    asset_server: ResMut<AssetServer>,
-    active_dialogue: ResMut<ActiveDialogue>,
-    flags: ResMut<GameFlags>,
-    mut comms_window: Query<(Entity,&mut Text2d), With<CommsWindow>>,
+/// How fast the typewriter effect shows a message.
+pub const CHARS_PER_SECOND: f32 = 40.0;
+/// Roughly how many characters fit on a line of the panel.
+pub const LINE_CHARS: usize = 50;
+/// How many lines of a page the panel shows before it scrolls.
+pub const VISIBLE_LINES: usize = 7;
+pub const CONTINUE_PROMPT: &str = "[Enter] continue";
+
+/// The current node's text and choices, localized and filled in as the player sees
+/// them.
+#[derive(SystemParam)]
+struct DialogueText<'w, 's> {
+    active_dialogue: Res<'w, ActiveDialogue>,
+    flags: Res<'w, GameFlags>,
+    strings: Res<'w, Localization>,
+    bodies: Query<'w, 's, &'static SolarBody>,
+    ship: Single<'w, &'static SpacePosition, With<MyShip>>,
+    config: Res<'w, BackgroundStarConfig>,
+    /// Templates already warned about, so a broken one isn't reported every frame.
+    broken: Local<'s, HashSet<String>>,
+}
+
+impl DialogueText<'_, '_> {
+    // The lint reports bad templates, so here they're warned about and shown as written.
+    fn show(&mut self, text: &str) -> String {
+        let context = TemplateContext {
+            flags: &self.flags,
+            speaker: self.speaker_name(),
+            ship: Some(ShipStats {
+                speed: self.config.speed,
+                direction: self.config.direction,
+                position: self.ship.0,
+            }),
+        };
+        let key = text;
+        let text = self.strings.get(key);
+        match render(text, &context) {
+            Ok(text) => text,
+            Err(e) => {
+                if self.broken.insert(key.to_string()) {
+                    warn!(
+                        "Couldn't fill in {key:?} at node `{}`: {e}",
+                        self.active_dialogue.node_id()
+                    );
+                }
+                text.to_string()
+            }
+        }
+    }
+    fn speaker(&self) -> Option<&Speaker> {
+        self.active_dialogue.get_speaker(&self.flags)
+    }
+    /// The speaker's own name, or the name of the body being talked to.
+    fn speaker_name(&self) -> Option<&str> {
+        let name = self.speaker().and_then(|s| s.name.as_deref());
+        name.map(|name| self.strings.get(name)).or_else(|| {
+            let entity = self.active_dialogue.entity?;
+            self.bodies.get(entity).ok().map(|body| body.name.as_str())
+        })
+    }
+    fn is_open(&self) -> bool {
+        self.active_dialogue.get_message(&self.flags).is_some()
+    }
+    fn line(&self) -> RevealedLine {
+        let active = &self.active_dialogue;
+        (active.entity, active.node_id(), active.text_index)
+    }
+    fn message(&mut self) -> Option<String> {
+        let message = self.active_dialogue.get_message(&self.flags)?.to_string();
+        Some(self.show(&message))
+    }
+    /// Every choice to list, with hints after the greyed out ones.
+    fn choices(&mut self) -> Vec<ChoiceLine> {
+        let list = self.active_dialogue.choice_list(&self.flags);
+        let indices = choice_indices(&list);
+        list.iter()
+            .zip(indices)
+            .map(|((choice, _), index)| {
+                let text = self.show(&choice.text);
+                let label = match (index, choice.hint.as_deref()) {
+                    (Some(_), _) => text,
+                    (None, hint) => {
+                        format!(
+                            "-  {text} ({})",
+                            hint.map(|h| self.show(h)).unwrap_or_default()
+                        )
+                    }
+                };
+                ChoiceLine { label, index }
+            })
+            .collect()
+    }
+}
+
+/// Who is speaking, the node they're at and the text picked for it.
+pub type RevealedLine = (Option<Entity>, String, Option<usize>);
+
+/// Shows a message a few characters at a time. Blank lines split it into pages the
+/// player steps through, and pages longer than the panel scroll.
+#[derive(Resource, Default)]
+pub struct TextReveal {
+    line: RevealedLine,
+    message: String,
+    pages: Vec<Vec<String>>,
+    page: usize,
+    shown: f32,
+    scroll: usize,
+    // Set once the player scrolls, so the reveal stops following the last line.
+    scrolled: bool,
+}
+
+impl TextReveal {
+    /// Starts revealing `message` when `line` changes. Otherwise the message is only
+    /// updated, since templates such as `{ship.speed}` change while it's shown.
+    pub fn set(&mut self, line: RevealedLine, message: &str) {
+        if line != self.line {
+            *self = TextReveal { line, ..default() };
+        }
+        if message != self.message {
+            self.message = message.to_string();
+            self.pages = paginate(message);
+            self.page = self.page.min(self.pages.len().saturating_sub(1));
+            self.shown = self.shown.min(self.page_len() as f32);
+        }
+    }
+    pub fn tick(&mut self, seconds: f32) {
+        self.shown = (self.shown + seconds * CHARS_PER_SECOND).min(self.page_len() as f32);
+        if !self.scrolled {
+            let (line, _) = self.cursor();
+            self.scroll = (line + 1).saturating_sub(VISIBLE_LINES);
+        }
+    }
+    /// Shows the rest of the page, or turns to the next one if it's all shown.
+    /// Returns false if the whole message was already shown, so the press can take a
+    /// choice instead.
+    pub fn skip(&mut self) -> bool {
+        if !self.page_done() {
+            self.shown = self.page_len() as f32;
+        } else if self.page + 1 < self.pages.len() {
+            self.page += 1;
+            self.shown = 0.0;
+            self.scroll = 0;
+            self.scrolled = false;
+        } else {
+            return false;
+        }
+        true
+    }
+    /// The line being revealed, which catches up with the dialogue once a frame.
+    pub fn line(&self) -> &RevealedLine {
+        &self.line
+    }
+    pub fn scroll_by(&mut self, lines: isize) {
+        let max = self.lines().len().saturating_sub(VISIBLE_LINES);
+        self.scroll = self.scroll.saturating_add_signed(lines).min(max);
+        self.scrolled = true;
+    }
+    /// Whether the whole message has been shown, so the choices can be.
+    pub fn is_done(&self) -> bool {
+        self.page + 1 >= self.pages.len() && self.page_done()
+    }
+    fn lines(&self) -> &[String] {
+        self.pages.get(self.page).map_or(&[], |p| p.as_slice())
+    }
+    fn page_len(&self) -> usize {
+        self.lines().iter().map(|l| l.chars().count()).sum()
+    }
+    fn page_done(&self) -> bool {
+        self.shown as usize >= self.page_len()
+    }
+    /// The line the reveal has reached and how many of its characters are shown.
+    fn cursor(&self) -> (usize, usize) {
+        let mut left = self.shown as usize;
+        for (index, line) in self.lines().iter().enumerate() {
+            let len = line.chars().count();
+            if left <= len {
+                return (index, left);
+            }
+            left -= len;
+        }
+        (self.lines().len().saturating_sub(1), 0)
+    }
+    /// The revealed part of the page that fits in the panel.
+    pub fn visible_text(&self) -> String {
+        let (cursor_line, cursor_chars) = self.cursor();
+        let mut lines = vec![];
+        for (index, line) in self.lines().iter().enumerate().skip(self.scroll) {
+            if self.page_done() || index < cursor_line {
+                lines.push(line.clone());
+            } else {
+                lines.push(line.chars().take(cursor_chars).collect());
+                break;
+            }
+            if lines.len() == VISIBLE_LINES {
+                break;
+            }
+        }
+        if self.page_done() && !self.is_done() {
+            lines.push(CONTINUE_PROMPT.to_string());
+        }
+        lines.join("\n")
+    }
+}
+
+/// Splits a message into pages at blank lines and word-wraps each page to the panel.
+pub fn paginate(message: &str) -> Vec<Vec<String>> {
+    message
+        .split("\n\n")
+        .map(|page| page.lines().flat_map(wrap).collect::<Vec<_>>())
+        .filter(|lines| !lines.is_empty())
+        .collect()
+}
+
+/// Breaks a line between words so each piece fits across the panel.
+pub fn wrap(line: &str) -> Vec<String> {
+    let mut lines = vec![];
+    let mut current = String::new();
+    for word in line.split_whitespace() {
+        let len = current.chars().count();
+        if len > 0 && len + 1 + word.chars().count() > LINE_CHARS {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    lines.push(current);
+    lines
+}
+
+fn reveal_text(
+    time: Res<Time>,
+    actions: Res<ActionState<GameActions>>,
+    mut text: DialogueText,
+    mut reveal: ResMut<TextReveal>,
 ) {
-    if !comms_window.is_empty() {
-        let entity = comms_window.single_mut().0;
-        cmd.entity(entity).despawn_descendants();
-        cmd.entity(entity).despawn_recursive();
-    }
-    if let Some(message) = active_dialogue.get_message(&flags) {
-        if comms_window.is_empty() {
-            let image = asset_server.load("9_patch_scifi.png");
-            let entity = cmd
-                .spawn((
-                    CommsWindow,
-                    Sprite {
-                        image: image.clone(),
-                        custom_size: Some(Vec2::new(400.0, 200.0)),
-                        image_mode: SpriteImageMode::Sliced(TextureSlicer {
-                            border: BorderRect::square(32.0),
-                            ..default()
-                        }),
-                        ..default()
-                    },
-                    Transform::from_translation(Vec3::new(100.0, 100.0, 100.0)),
-                ))
-                .with_child((
-                    Transform::from_xyz(0.0, 0.0, 10.0),
-                    TextLayout::new(JustifyText::Left, LineBreak::WordBoundary),
-                    TextBounds::from(Vec2::new(400.0, 200.0)),
-                    Text2d(message.to_string()),
-                ))
-                .id();
-            if let Some(choices) = active_dialogue.get_choices(&flags) {
-                let choices_message = choices
-                    .iter()
-                    .map(|c| c.text.as_ref())
-                    .collect::<Vec<&str>>()
-                    .join("\n");
-                cmd.entity(entity)
-                    .with_child((
-                        Transform::from_xyz(0.0, -200.0, 10.0),
-                        Sprite {
-                            image,
-                            custom_size: Some(Vec2::new(400.0, 200.0)),
-                            image_mode: SpriteImageMode::Sliced(TextureSlicer {
-                                border: BorderRect::square(32.0),
-                                ..default()
-                            }),
+    reveal.set(text.line(), &text.message().unwrap_or_default());
+    reveal.tick(time.delta_secs());
+    if actions.just_pressed(GameActions::ScrollCommsUp) {
+        reveal.scroll_by(-1);
+    }
+    if actions.just_pressed(GameActions::ScrollCommsDown) {
+        reveal.scroll_by(1);
+    }
+}
+
+/// How many choices the panel lists at once. Moving the highlight past either end
+/// turns the page. There's a digit key for each, 1 to 9.
+pub const CHOICES_PER_PAGE: usize = 9;
+
+/// For each listed choice, the index `ActiveDialogue::choose` takes for it, or
+/// `None` if it's greyed out.
+fn choice_indices(list: &[(Choice, bool)]) -> Vec<Option<usize>> {
+    let mut next = 0;
+    list.iter()
+        .map(|(_, enabled)| {
+            enabled.then(|| {
+                next += 1;
+                next - 1
+            })
+        })
+        .collect()
+}
+
+/// The part of the list on the page that shows the choice at `selected`.
+pub fn choice_page<T>(
+    list: &[T],
+    index: impl Fn(&T) -> Option<usize>,
+    selected: usize,
+) -> Range<usize> {
+    let position = list
+        .iter()
+        .position(|item| index(item) == Some(selected))
+        .unwrap_or(0);
+    let start = position - position % CHOICES_PER_PAGE;
+    start..list.len().min(start + CHOICES_PER_PAGE)
+}
+
+/// The choice the keyboard has highlighted, as an index into the ones that can be
+/// taken. It goes back to the first choice whenever the line shown changes.
+#[derive(Resource, Default)]
+pub struct ChoiceCursor {
+    line: RevealedLine,
+    pub selected: usize,
+}
+
+/// Enter shows the rest of the message first, then takes the highlighted choice. Up
+/// and down move the highlight, and hovering a choice moves it too so the mouse and
+/// keyboard agree. Digit keys take the choices numbered on the current page.
+fn choose_choice(
+    mut commands: Commands,
+    actions: Res<ActionState<GameActions>>,
+    mut active_dialogue: ResMut<ActiveDialogue>,
+    mut flags: ResMut<GameFlags>,
+    mut reveal: ResMut<TextReveal>,
+    mut cursor: ResMut<ChoiceCursor>,
+    buttons: Query<(&Interaction, &ChoiceButton), Changed<Interaction>>,
+) {
+    if active_dialogue.dialogue.is_none() {
+        return;
+    }
+    let line = (
+        active_dialogue.entity,
+        active_dialogue.node_id(),
+        active_dialogue.text_index,
+    );
+    if cursor.line != line {
+        *cursor = ChoiceCursor { line, selected: 0 };
+    }
+    // Right after a choice the reveal still holds the last line, whose choices are gone.
+    if reveal.line() != &cursor.line {
+        return;
+    }
+    // A press that shows more of the message doesn't also take a choice.
+    if actions.just_pressed(GameActions::SkipText) && reveal.skip() {
+        return;
+    }
+    if !reveal.is_done() {
+        return;
+    }
+    let indices = choice_indices(&active_dialogue.choice_list(&flags));
+    let count = indices.iter().flatten().count();
+    if count == 0 {
+        return;
+    }
+
+    let mut chosen = None;
+    for (interaction, button) in buttons.iter() {
+        let Some(index) = button.index else {
+            continue;
+        };
+        match interaction {
+            Interaction::Pressed => chosen = Some(index),
+            Interaction::Hovered => cursor.selected = index,
+            Interaction::None => {}
+        }
+    }
+    if actions.just_pressed(GameActions::ChoiceUp) {
+        cursor.selected = (cursor.selected + count - 1) % count;
+    }
+    if actions.just_pressed(GameActions::ChoiceDown) {
+        cursor.selected = (cursor.selected + 1) % count;
+    }
+    cursor.selected = cursor.selected.min(count - 1);
+    let page = &indices[choice_page(&indices, |i| *i, cursor.selected)];
+    for (number, index) in page.iter().flatten().enumerate() {
+        if actions.just_pressed(GameActions::Choose(number)) {
+            chosen = Some(*index);
+        }
+    }
+    if actions.just_pressed(GameActions::SkipText) {
+        chosen = Some(cursor.selected);
+    }
+    if let Some(index) = chosen {
+        active_dialogue.choose(index, &mut flags, &mut commands);
+    }
+}
+
+/// How long the comms window takes to open or close.
+const OPEN_SECONDS: f32 = 0.2;
+const PANEL_WIDTH: f32 = 560.0;
+const PORTRAIT_SIZE: f32 = 64.0;
+const TEXT_COLOR: Color = Color::WHITE;
+const DISABLED_COLOR: Color = Color::srgb(0.45, 0.45, 0.5);
+const MORE_ABOVE: &str = "(more choices above)";
+const MORE_BELOW: &str = "(more choices below)";
+const HIGHLIGHT_COLOR: Color = Color::srgba(0.3, 0.6, 1.0, 0.35);
+
+/// Spawns the comms window once, closed, as a panel along the bottom of the screen.
+/// `update_window` opens it, closes it and changes its text, and `update_choices`
+/// fills in the choice buttons, rather than rebuilding it.
+fn spawn_window(mut cmd: Commands, asset_server: Res<AssetServer>) {
+    let image = asset_server.load("9_patch_scifi.png");
+    cmd.spawn(Node {
+        position_type: PositionType::Absolute,
+        width: Val::Percent(100.0),
+        bottom: Val::Px(24.0),
+        justify_content: JustifyContent::Center,
+        ..default()
+    })
+    .with_children(|root| {
+        root.spawn((
+            CommsWindow::default(),
+            Node {
+                width: Val::Px(PANEL_WIDTH),
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(28.0)),
+                row_gap: Val::Px(12.0),
+                ..default()
+            },
+            ImageNode::new(image).with_mode(NodeImageMode::Sliced(TextureSlicer {
+                border: BorderRect::square(32.0),
+                ..default()
+            })),
+            Visibility::Hidden,
+        ))
+        .with_children(|window| {
+            window
+                .spawn(Node {
+                    align_items: AlignItems::Center,
+                    column_gap: Val::Px(12.0),
+                    ..default()
+                })
+                .with_children(|header| {
+                    header.spawn((
+                        CommsPortrait,
+                        ImageNode::default(),
+                        Node {
+                            width: Val::Px(PORTRAIT_SIZE),
+                            height: Val::Px(PORTRAIT_SIZE),
+                            display: Display::None,
                             ..default()
                         },
-                    ))
-                    .with_child((
-                        Transform::from_xyz(0.0, -200.0, 20.0),
-                        TextLayout::new(JustifyText::Left, LineBreak::WordBoundary),
-                        TextBounds::from(Vec2::new(400.0, 200.0)),
-                        Text2d(choices_message),
                     ));
-            }
-        } 
-    } 
+                    header.spawn((CommsSpeaker, Text::default(), TextColor(TEXT_COLOR)));
+                });
+            window.spawn((
+                CommsMessage,
+                Text::default(),
+                TextColor(TEXT_COLOR),
+                Node {
+                    min_height: Val::Px(VISIBLE_LINES as f32 * 24.0),
+                    ..default()
+                },
+            ));
+            window.spawn((
+                CommsChoices::default(),
+                Node {
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(2.0),
+                    ..default()
+                },
+            ));
+        });
+    });
+}
+
+fn update_window(
+    time: Res<Time>,
+    text: DialogueText,
+    reveal: Res<TextReveal>,
+    window: Single<(&mut CommsWindow, &mut Transform, &mut Visibility)>,
+    mut message: Single<&mut Text, With<CommsMessage>>,
+) {
+    let (mut window, mut transform, mut visibility) = window.into_inner();
+    let open = text.is_open();
+    let step = time.delta_secs() / OPEN_SECONDS;
+    let openness = if open {
+        (window.openness + step).min(1.0)
+    } else {
+        (window.openness - step).max(0.0)
+    };
+    if openness != window.openness {
+        window.openness = openness;
+        // Opens from a line across the middle, like an old CRT.
+        let eased = openness * openness * (3.0 - 2.0 * openness);
+        transform.scale = Vec3::new(1.0, eased.max(0.01), 1.0);
+    }
+    visibility.set_if_neq(if openness > 0.0 {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    });
+    // While closing, the window keeps showing what it last showed.
+    if !open {
+        return;
+    }
+
+    let visible = reveal.visible_text();
+    if message.0 != visible {
+        message.0 = visible;
+    }
+}
+
+/// Shows who's talking and styles the window for them, when that changes.
+fn update_speaker(
+    asset_server: Res<AssetServer>,
+    text: DialogueText,
+    window: Single<(&mut CommsWindow, &mut ImageNode), Without<CommsPortrait>>,
+    portrait: Single<(&mut ImageNode, &mut Node), With<CommsPortrait>>,
+    name: Single<(&mut Text, &mut TextColor), With<CommsSpeaker>>,
+    mut message: Single<&mut TextFont, With<CommsMessage>>,
+) {
+    if !text.is_open() {
+        return;
+    }
+    let speaker = text.speaker().cloned().unwrap_or_default();
+    let shown = (speaker, text.speaker_name().unwrap_or_default().to_string());
+    let (mut window, mut panel) = window.into_inner();
+    if window.speaker.as_ref() == Some(&shown) {
+        return;
+    }
+    let (speaker, speaker_name) = &shown;
+
+    let accent = speaker.accent_color();
+    panel.color = accent.unwrap_or(Color::WHITE);
+    let (mut name, mut name_color) = name.into_inner();
+    name.0.clone_from(speaker_name);
+    name_color.0 = accent.unwrap_or(TEXT_COLOR);
+    let (mut portrait, mut portrait_node) = portrait.into_inner();
+    match speaker.portrait.as_deref() {
+        Some(path) => {
+            portrait.image = asset_server.load(path);
+            portrait_node.display = Display::Flex;
+        }
+        None => portrait_node.display = Display::None,
+    }
+    message.font = speaker
+        .font
+        .as_deref()
+        .map(|path| asset_server.load(path))
+        .unwrap_or_default();
+    window.speaker = Some(shown);
+}
+
+/// Rebuilds the choice buttons when the list changes and highlights the selected one.
+fn update_choices(
+    mut commands: Commands,
+    mut text: DialogueText,
+    reveal: Res<TextReveal>,
+    cursor: Res<ChoiceCursor>,
+    list: Single<(Entity, &mut CommsChoices)>,
+    mut buttons: Query<(&ChoiceButton, &mut BackgroundColor)>,
+) {
+    if !text.is_open() {
+        return;
+    }
+    let (entity, mut list) = list.into_inner();
+    let lines = if reveal.is_done() {
+        page_lines(text.choices(), cursor.selected)
+    } else {
+        vec![]
+    };
+    if lines != list.shown {
+        commands
+            .entity(entity)
+            .despawn_descendants()
+            .with_children(|parent| {
+                for line in lines.iter() {
+                    spawn_choice(parent, line, cursor.selected);
+                }
+            });
+        list.shown = lines;
+        return;
+    }
+    for (button, mut background) in buttons.iter_mut() {
+        background.set_if_neq(choice_background(button.index, cursor.selected));
+    }
+}
+
+/// The lines on the page showing the selected choice, numbered for the digit keys,
+/// with a note above or below when there are more pages that way.
+pub fn page_lines(mut lines: Vec<ChoiceLine>, selected: usize) -> Vec<ChoiceLine> {
+    let page = choice_page(&lines, |line| line.index, selected);
+    let more = |label: &str| ChoiceLine {
+        label: label.to_string(),
+        index: None,
+    };
+    let (above, below) = (page.start > 0, page.end < lines.len());
+    let mut shown = lines.drain(page).collect::<Vec<_>>();
+    for (number, line) in shown.iter_mut().filter(|l| l.index.is_some()).enumerate() {
+        line.label = format!("{}. {}", number + 1, line.label);
+    }
+    if above {
+        shown.insert(0, more(MORE_ABOVE));
+    }
+    if below {
+        shown.push(more(MORE_BELOW));
+    }
+    shown
+}
+
+fn choice_background(index: Option<usize>, selected: usize) -> BackgroundColor {
+    if index == Some(selected) {
+        BackgroundColor(HIGHLIGHT_COLOR)
+    } else {
+        BackgroundColor(Color::NONE)
+    }
+}
+
+fn spawn_choice(parent: &mut ChildBuilder, line: &ChoiceLine, selected: usize) {
+    let color = if line.index.is_some() {
+        TEXT_COLOR
+    } else {
+        DISABLED_COLOR
+    };
+    parent
+        .spawn((
+            Button,
+            ChoiceButton { index: line.index },
+            Node {
+                padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+                ..default()
+            },
+            choice_background(line.index, selected),
+        ))
+        .with_child((Text::new(line.label.clone()), TextColor(color)));
+}
+
+#[derive(Component, Default)]
+pub struct CommsWindow {
+    /// 0 when closed, 1 when fully open.
+    openness: f32,
+    /// The speaker the window is styled for, and the name it shows.
+    speaker: Option<(Speaker, String)>,
+}
+
+#[derive(Component)]
+pub struct CommsPortrait;
+
+#[derive(Component)]
+pub struct CommsSpeaker;
+
+#[derive(Component)]
+pub struct CommsMessage;
+
+/// The column of choice buttons, and the choices it was last filled with.
+#[derive(Component, Default)]
+pub struct CommsChoices {
+    shown: Vec<ChoiceLine>,
+}
+
+/// A choice as listed in the comms window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChoiceLine {
+    pub label: String,
+    /// What `ActiveDialogue::choose` takes, or `None` for a greyed out choice.
+    pub index: Option<usize>,
 }
 
 #[derive(Component)]
-pub struct CommsWindow;
+pub struct ChoiceButton {
+    pub index: Option<usize>,
+}
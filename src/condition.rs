@@ -0,0 +1,449 @@
+use crate::story_system::GameFlags;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Debug, Display};
+use std::ops::Range;
+
+/// A dialogue condition such as `has_job && !(has_delivered || credits < 100)`,
+/// parsed once when the dialogue is deserialized.
+#[derive(Deserialize, Clone)]
+#[serde(try_from = "String")]
+pub struct Condition {
+    source: String,
+    expr: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(Value),
+    Variable(String),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(CompareOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Value {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConditionError {
+    pub source: String,
+    pub message: String,
+    pub span: Range<usize>,
+}
+
+impl Condition {
+    pub fn parse(source: &str) -> Result<Self, ConditionError> {
+        if source.trim().is_empty() {
+            return Ok(Self {
+                source: source.to_string(),
+                expr: Expr::Literal(Value::Bool(true)),
+            });
+        }
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            source,
+            tokens: &tokens,
+            index: 0,
+        };
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            return Err(parser.error(
+                "expected `&&`, `||` or end of condition",
+                token.span.clone(),
+            ));
+        }
+        Ok(Self {
+            source: source.to_string(),
+            expr,
+        })
+    }
+    pub fn evaluate(&self, flags: &GameFlags) -> bool {
+        self.expr.evaluate(flags).is_truthy()
+    }
+    /// The names of every flag or variable this condition reads.
+    pub fn variables(&self) -> Vec<&str> {
+        let mut names = vec![];
+        self.expr.visit_variables(&mut |name| names.push(name));
+        names
+    }
+}
+
+/// Evaluates an optional condition, where a missing condition always passes.
+pub fn check(condition: Option<&Condition>, flags: &GameFlags) -> bool {
+    condition.is_none_or(|c| c.evaluate(flags))
+}
+
+impl TryFrom<String> for Condition {
+    type Error = ConditionError;
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        Condition::parse(&source)
+    }
+}
+
+impl Debug for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.source)
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl Expr {
+    pub fn evaluate(&self, flags: &GameFlags) -> Value {
+        match self {
+            Expr::Literal(value) => value.clone(),
+            Expr::Variable(name) => flags.value(name),
+            Expr::Not(expr) => Value::Bool(!expr.evaluate(flags).is_truthy()),
+            Expr::And(lhs, rhs) => {
+                Value::Bool(lhs.evaluate(flags).is_truthy() && rhs.evaluate(flags).is_truthy())
+            }
+            Expr::Or(lhs, rhs) => {
+                Value::Bool(lhs.evaluate(flags).is_truthy() || rhs.evaluate(flags).is_truthy())
+            }
+            Expr::Compare(op, lhs, rhs) => {
+                Value::Bool(op.apply(&lhs.evaluate(flags), &rhs.evaluate(flags)))
+            }
+        }
+    }
+
+    fn visit_variables<'a>(&'a self, f: &mut impl FnMut(&'a str)) {
+        match self {
+            Expr::Literal(_) => {}
+            Expr::Variable(name) => f(name),
+            Expr::Not(expr) => expr.visit_variables(f),
+            Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) | Expr::Compare(_, lhs, rhs) => {
+                lhs.visit_variables(f);
+                rhs.visit_variables(f);
+            }
+        }
+    }
+}
+
+impl Value {
+    /// Parses an action argument such as `50`, `2.5`, `true` or `miners`.
+    /// Anything that isn't a number or boolean is a string, optionally quoted.
+    pub fn parse_literal(text: &str) -> Self {
+        let text = text.trim();
+        if let Ok(i) = text.parse::<i64>() {
+            Value::Int(i)
+        } else if let Ok(f) = text.parse::<f64>() {
+            Value::Float(f)
+        } else if let Ok(b) = text.parse::<bool>() {
+            Value::Bool(b)
+        } else {
+            let unquoted = text
+                .strip_prefix('"')
+                .and_then(|t| t.strip_suffix('"'))
+                .unwrap_or(text);
+            Value::Str(unquoted.to_string())
+        }
+    }
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Bool(b) => *b,
+            Value::Int(i) => *i != 0,
+            Value::Float(f) => *f != 0.0,
+            Value::Str(s) => !s.is_empty(),
+        }
+    }
+}
+
+impl CompareOp {
+    fn apply(self, lhs: &Value, rhs: &Value) -> bool {
+        use std::cmp::Ordering;
+        // Unset variables read as `false`, so booleans compare as 0/1 against numbers.
+        let ordering = match (lhs, rhs) {
+            (Value::Bool(a), Value::Bool(b)) => a.partial_cmp(b),
+            (Value::Bool(a), Value::Int(b)) => (*a as i64).partial_cmp(b),
+            (Value::Int(a), Value::Bool(b)) => a.partial_cmp(&(*b as i64)),
+            (Value::Bool(a), Value::Float(b)) => (*a as i64 as f64).partial_cmp(b),
+            (Value::Float(a), Value::Bool(b)) => a.partial_cmp(&(*b as i64 as f64)),
+            (Value::Int(a), Value::Int(b)) => a.partial_cmp(b),
+            (Value::Int(a), Value::Float(b)) => (*a as f64).partial_cmp(b),
+            (Value::Float(a), Value::Int(b)) => a.partial_cmp(&(*b as f64)),
+            (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+            (Value::Str(a), Value::Str(b)) => a.partial_cmp(b),
+            _ => None,
+        };
+        match (self, ordering) {
+            (CompareOp::Eq, ordering) => ordering == Some(Ordering::Equal),
+            (CompareOp::Ne, ordering) => ordering != Some(Ordering::Equal),
+            (_, None) => false,
+            (CompareOp::Lt, Some(ordering)) => ordering.is_lt(),
+            (CompareOp::Le, Some(ordering)) => ordering.is_le(),
+            (CompareOp::Gt, Some(ordering)) => ordering.is_gt(),
+            (CompareOp::Ge, Some(ordering)) => ordering.is_ge(),
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Int(i) => write!(f, "{i}"),
+            Value::Float(x) => write!(f, "{x}"),
+            Value::Str(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+impl Display for ConditionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid condition `{}`: {} at {}..{}",
+            self.source, self.message, self.span.start, self.span.end
+        )
+    }
+}
+
+impl std::error::Error for ConditionError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Ident(String),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Not,
+    And,
+    Or,
+    Compare(CompareOp),
+    LeftParen,
+    RightParen,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    span: Range<usize>,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, ConditionError> {
+    let error = |message: &str, span: Range<usize>| ConditionError {
+        source: source.to_string(),
+        message: message.to_string(),
+        span,
+    };
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some(&(start, ch)) = chars.peek() {
+        chars.next();
+        let next = chars.peek().map(|&(_, c)| c);
+        let kind = match (ch, next) {
+            _ if ch.is_whitespace() => continue,
+            ('(', _) => TokenKind::LeftParen,
+            (')', _) => TokenKind::RightParen,
+            ('&', Some('&')) => {
+                chars.next();
+                TokenKind::And
+            }
+            ('|', Some('|')) => {
+                chars.next();
+                TokenKind::Or
+            }
+            ('=', Some('=')) => {
+                chars.next();
+                TokenKind::Compare(CompareOp::Eq)
+            }
+            ('!', Some('=')) => {
+                chars.next();
+                TokenKind::Compare(CompareOp::Ne)
+            }
+            ('<', Some('=')) => {
+                chars.next();
+                TokenKind::Compare(CompareOp::Le)
+            }
+            ('>', Some('=')) => {
+                chars.next();
+                TokenKind::Compare(CompareOp::Ge)
+            }
+            ('!', _) => TokenKind::Not,
+            ('<', _) => TokenKind::Compare(CompareOp::Lt),
+            ('>', _) => TokenKind::Compare(CompareOp::Gt),
+            ('&', _) => return Err(error("expected `&&`", start..start + 1)),
+            ('|', _) => return Err(error("expected `||`", start..start + 1)),
+            ('=', _) => return Err(error("expected `==`", start..start + 1)),
+            ('"', _) => {
+                let mut text = String::new();
+                let mut closed = false;
+                while let Some((_, c)) = chars.next() {
+                    match c {
+                        '"' => {
+                            closed = true;
+                            break;
+                        }
+                        '\\' => match chars.next() {
+                            Some((_, escaped)) => text.push(escaped),
+                            None => break,
+                        },
+                        _ => text.push(c),
+                    }
+                }
+                if !closed {
+                    return Err(error("unterminated string", start..source.len()));
+                }
+                TokenKind::Str(text)
+            }
+            _ if ch.is_ascii_digit() || (ch == '-' && next.is_some_and(|c| c.is_ascii_digit())) => {
+                let mut end = start + ch.len_utf8();
+                while let Some(&(i, c)) = chars.peek() {
+                    if c.is_ascii_digit() || c == '.' {
+                        end = i + c.len_utf8();
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let text = &source[start..end];
+                if let Ok(int) = text.parse::<i64>() {
+                    TokenKind::Int(int)
+                } else if let Ok(float) = text.parse::<f64>() {
+                    TokenKind::Float(float)
+                } else {
+                    return Err(error("invalid number", start..end));
+                }
+            }
+            _ if ch.is_alphanumeric() || ch == '_' => {
+                let mut end = start + ch.len_utf8();
+                while let Some(&(i, c)) = chars.peek() {
+                    if c.is_alphanumeric() || c == '_' {
+                        end = i + c.len_utf8();
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                TokenKind::Ident(source[start..end].to_string())
+            }
+            _ => {
+                return Err(error(
+                    &format!("unexpected character `{ch}`"),
+                    start..start + ch.len_utf8(),
+                ));
+            }
+        };
+        let end = chars.peek().map_or(source.len(), |&(i, _)| i);
+        tokens.push(Token {
+            kind,
+            span: start..end,
+        });
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: &'a [Token],
+    index: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index)
+    }
+    fn next_if(&mut self, kind: &TokenKind) -> bool {
+        if self.peek().is_some_and(|t| &t.kind == kind) {
+            self.index += 1;
+            true
+        } else {
+            false
+        }
+    }
+    fn error(&self, message: &str, span: Range<usize>) -> ConditionError {
+        ConditionError {
+            source: self.source.to_string(),
+            message: message.to_string(),
+            span,
+        }
+    }
+    fn end_span(&self) -> Range<usize> {
+        self.source.len()..self.source.len()
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, ConditionError> {
+        let mut expr = self.parse_and()?;
+        while self.next_if(&TokenKind::Or) {
+            let rhs = self.parse_and()?;
+            expr = Expr::Or(Box::new(expr), Box::new(rhs));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ConditionError> {
+        let mut expr = self.parse_unary()?;
+        while self.next_if(&TokenKind::And) {
+            let rhs = self.parse_unary()?;
+            expr = Expr::And(Box::new(expr), Box::new(rhs));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ConditionError> {
+        if self.next_if(&TokenKind::Not) {
+            Ok(Expr::Not(Box::new(self.parse_unary()?)))
+        } else {
+            self.parse_comparison()
+        }
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, ConditionError> {
+        let lhs = self.parse_primary()?;
+        if let Some(TokenKind::Compare(op)) = self.peek().map(|t| t.kind.clone()) {
+            self.index += 1;
+            let rhs = self.parse_primary()?;
+            Ok(Expr::Compare(op, Box::new(lhs), Box::new(rhs)))
+        } else {
+            Ok(lhs)
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ConditionError> {
+        let Some(token) = self.peek().cloned() else {
+            return Err(self.error("expected a flag or value", self.end_span()));
+        };
+        self.index += 1;
+        match token.kind {
+            TokenKind::LeftParen => {
+                let expr = self.parse_or()?;
+                if !self.next_if(&TokenKind::RightParen) {
+                    let span = self.peek().map_or(self.end_span(), |t| t.span.clone());
+                    return Err(self.error("expected `)`", span));
+                }
+                Ok(expr)
+            }
+            TokenKind::Ident(name) => Ok(match name.as_str() {
+                "true" => Expr::Literal(Value::Bool(true)),
+                "false" => Expr::Literal(Value::Bool(false)),
+                _ => Expr::Variable(name),
+            }),
+            TokenKind::Int(i) => Ok(Expr::Literal(Value::Int(i))),
+            TokenKind::Float(f) => Ok(Expr::Literal(Value::Float(f))),
+            TokenKind::Str(s) => Ok(Expr::Literal(Value::Str(s))),
+            _ => Err(self.error("expected a flag or value", token.span)),
+        }
+    }
+}
@@ -0,0 +1,354 @@
+use crate::condition::Value;
+use crate::story_system::{ActiveDialogue, GameFlags};
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::sync::{Arc, RwLock};
+
+/// A dialogue action such as `set_flag:has_job` or `add:credits:50`, split into
+/// its name and arguments when the dialogue is deserialized.
+///
+/// Built-in actions change [`GameFlags`] as soon as they're reached. Registered ones
+/// are queued and run in order once commands are applied, after all the built-in
+/// ones taken with them, so `["resume:later", "set_flag:met"]` sets `met` first.
+#[derive(Deserialize, Clone)]
+#[serde(try_from = "String")]
+pub struct Action {
+    pub name: String,
+    // Everything after the name, split up by `args` once it's known how many
+    // arguments the action takes.
+    rest: Option<String>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ArgType {
+    Str,
+    Int,
+    Number,
+    Bool,
+    Any,
+}
+
+/// The arguments of an action, already checked against its registered [`ArgType`]s.
+pub struct ActionArgs<'a>(Vec<&'a str>);
+
+/// What a registered handler gets when one of its actions runs.
+pub struct ActionCall<'a> {
+    pub speaker: Option<Entity>,
+    pub args: ActionArgs<'a>,
+}
+
+type ActionHandler = Arc<dyn Fn(&mut World, &ActionCall) + Send + Sync>;
+
+struct RegisteredAction {
+    args: Vec<ArgType>,
+    handler: Option<ActionHandler>,
+}
+
+/// Every action a dialogue may use. The flag and variable actions are built in and
+/// applied straight to [`GameFlags`]; everything else, like `resume:<node>`, is
+/// registered with [`DialogueActionsAppExt::register_dialogue_action`] and runs with
+/// the `World`.
+/// Clones share the same actions, so the dialogue loader sees later registrations.
+#[derive(Resource, Clone)]
+pub struct ActionRegistry {
+    actions: Arc<RwLock<HashMap<String, RegisteredAction>>>,
+}
+
+impl Default for ActionRegistry {
+    fn default() -> Self {
+        use ArgType::*;
+        let mut actions = HashMap::new();
+        for (name, args) in [
+            ("set_flag", vec![Str]),
+            ("remove_flag", vec![Str]),
+            ("set", vec![Str, Any]),
+            ("add", vec![Str, Number]),
+            ("sub", vec![Str, Number]),
+            ("unset", vec![Str]),
+        ] {
+            actions.insert(
+                name.to_string(),
+                RegisteredAction {
+                    args,
+                    handler: None,
+                },
+            );
+        }
+        let mut registry = Self {
+            actions: Arc::new(RwLock::new(actions)),
+        };
+        registry.register("resume", &[Str], |world, call| {
+            if let Some(speaker) = call.speaker {
+                let node_id = call.args.str(0).to_string();
+                world
+                    .resource_mut::<ActiveDialogue>()
+                    .resume
+                    .insert(speaker, node_id);
+            }
+        });
+        registry
+    }
+}
+
+impl ActionRegistry {
+    /// Adds an action for dialogues to use.
+    ///
+    /// # Panics
+    ///
+    /// If an action with that name, built in or not, is already registered.
+    pub fn register(
+        &mut self,
+        name: &str,
+        args: &[ArgType],
+        handler: impl Fn(&mut World, &ActionCall) + Send + Sync + 'static,
+    ) {
+        let mut actions = self.actions.write().unwrap();
+        assert!(
+            !actions.contains_key(name),
+            "dialogue action `{name}` is already registered"
+        );
+        actions.insert(
+            name.to_string(),
+            RegisteredAction {
+                args: args.to_vec(),
+                handler: Some(Arc::new(handler)),
+            },
+        );
+    }
+
+    /// Whether an action with this name has been registered.
+    pub fn contains(&self, name: &str) -> bool {
+        self.actions.read().unwrap().contains_key(name)
+    }
+
+    /// Whether an action is built in, and so applied as soon as it's reached.
+    pub fn is_built_in(&self, name: &str) -> bool {
+        let actions = self.actions.read().unwrap();
+        actions.get(name).is_some_and(|a| a.handler.is_none())
+    }
+
+    /// Checks that an action exists and that its arguments match what was registered.
+    pub fn validate(&self, action: &Action) -> Result<(), String> {
+        let actions = self.actions.read().unwrap();
+        let Some(registered) = actions.get(&action.name) else {
+            return Err(format!("unknown action `{action}`"));
+        };
+        let args = action.args(registered.args.len());
+        if registered.args.len() != args.len() {
+            return Err(format!(
+                "action `{action}` takes {} argument(s), found {}",
+                registered.args.len(),
+                args.len()
+            ));
+        }
+        for (index, (arg_type, arg)) in registered.args.iter().zip(&args.0).enumerate() {
+            if !arg_type.accepts(arg) {
+                return Err(format!(
+                    "action `{action}` expects {} for argument {}, found `{arg}`",
+                    arg_type.describe(),
+                    index + 1
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The handler for a registered action, with how many arguments it takes.
+    fn handler(&self, name: &str) -> Option<(usize, ActionHandler)> {
+        let actions = self.actions.read().unwrap();
+        let action = actions.get(name)?;
+        Some((action.args.len(), action.handler.clone()?))
+    }
+}
+
+pub trait DialogueActionsAppExt {
+    fn register_dialogue_action(
+        &mut self,
+        name: &str,
+        args: &[ArgType],
+        handler: impl Fn(&mut World, &ActionCall) + Send + Sync + 'static,
+    ) -> &mut Self;
+}
+
+impl DialogueActionsAppExt for App {
+    fn register_dialogue_action(
+        &mut self,
+        name: &str,
+        args: &[ArgType],
+        handler: impl Fn(&mut World, &ActionCall) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.init_resource::<ActionRegistry>();
+        self.world_mut()
+            .resource_mut::<ActionRegistry>()
+            .register(name, args, handler);
+        self
+    }
+}
+
+impl Action {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let (name, rest) = match source.split_once(':') {
+            Some((name, rest)) => (name, Some(rest)),
+            None => (source, None),
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(format!("action `{source}` has no name"));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            rest: rest.map(|rest| rest.to_string()),
+        })
+    }
+    /// The arguments, split at `:` into at most `count` of them. The last one keeps
+    /// any further `:`, so `set:motto:"a:b"` sets `motto` to `"a:b"`.
+    pub fn args(&self, count: usize) -> ActionArgs<'_> {
+        let args = match self.rest.as_deref() {
+            Some(rest) => rest.splitn(count.max(1), ':').collect(),
+            None => vec![],
+        };
+        ActionArgs(args)
+    }
+}
+
+impl TryFrom<String> for Action {
+    type Error = String;
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        Action::parse(&source)
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(rest) = self.rest.as_ref() {
+            write!(f, ":{rest}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Debug for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.to_string())
+    }
+}
+
+impl ArgType {
+    fn accepts(self, arg: &str) -> bool {
+        match self {
+            ArgType::Str | ArgType::Any => true,
+            ArgType::Int => arg.trim().parse::<i64>().is_ok(),
+            ArgType::Number => arg.trim().parse::<f64>().is_ok(),
+            ArgType::Bool => arg.trim().parse::<bool>().is_ok(),
+        }
+    }
+    fn describe(self) -> &'static str {
+        match self {
+            ArgType::Str => "a string",
+            ArgType::Int => "an integer",
+            ArgType::Number => "a number",
+            ArgType::Bool => "`true` or `false`",
+            ArgType::Any => "a value",
+        }
+    }
+}
+
+impl ActionArgs<'_> {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    pub fn str(&self, index: usize) -> &str {
+        self.0.get(index).copied().unwrap_or_default()
+    }
+    pub fn int(&self, index: usize) -> i64 {
+        self.str(index).trim().parse().unwrap_or_default()
+    }
+    pub fn bool(&self, index: usize) -> bool {
+        self.str(index).trim().parse().unwrap_or_default()
+    }
+    pub fn value(&self, index: usize) -> Value {
+        Value::parse_literal(self.str(index))
+    }
+}
+
+/// Runs a registered action's handler once commands are applied.
+struct RunAction {
+    action: Action,
+    speaker: Option<Entity>,
+}
+
+impl Command for RunAction {
+    fn apply(self, world: &mut World) {
+        let handler = world
+            .get_resource::<ActionRegistry>()
+            .and_then(|registry| registry.handler(&self.action.name));
+        if let Some((count, handler)) = handler {
+            let call = ActionCall {
+                speaker: self.speaker,
+                args: self.action.args(count),
+            };
+            handler(world, &call);
+        } else {
+            warn!("Unknown action: {}", self.action);
+        }
+    }
+}
+
+/// Applies the built-in actions in `actions` and queues the rest, see [`Action`].
+pub fn perform_actions(
+    actions: &[Action],
+    state: &mut GameFlags,
+    commands: &mut Commands,
+    speaker: Option<Entity>,
+) {
+    for action in actions {
+        if !state.changed_by(speaker, |state| perform_action(action, state)) {
+            commands.queue(RunAction {
+                action: action.clone(),
+                speaker,
+            });
+        }
+    }
+}
+
+// Perform a built-in action to modify the game state, returning false for any
+// other action so the caller can hand it to the registry.
+pub fn perform_action(action: &Action, state: &mut GameFlags) -> bool {
+    let args = match action.name.as_str() {
+        "set_flag" | "remove_flag" | "unset" => action.args(1),
+        _ => action.args(2),
+    };
+    match action.name.as_str() {
+        "set_flag" => state.set(args.str(0)),
+        "remove_flag" => state.remove(args.str(0)),
+        "set" => state.set_var(args.str(0), args.value(1)),
+        "add" => {
+            if let Err(e) = state.add_var(args.str(0), &args.value(1)) {
+                warn!("Invalid action {}: {}", action, e);
+            }
+        }
+        "sub" => {
+            let result = match args.value(1) {
+                Value::Int(i) => i
+                    .checked_neg()
+                    .map(Value::Int)
+                    .ok_or_else(|| format!("can't subtract {i}")),
+                Value::Float(f) => Ok(Value::Float(-f)),
+                other => Ok(other),
+            }
+            .and_then(|negated| state.add_var(args.str(0), &negated));
+            if let Err(e) = result {
+                warn!("Invalid action {}: {}", action, e);
+            }
+        }
+        "unset" => state.remove_var(args.str(0)),
+        _ => return false,
+    }
+    true
+}
@@ -0,0 +1,207 @@
+use crate::story_system::{Choice, Dialogue, END_NODE};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+}
+
+impl GraphFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "dot" => Some(GraphFormat::Dot),
+            "mermaid" => Some(GraphFormat::Mermaid),
+            _ => None,
+        }
+    }
+}
+
+const MAX_LABEL: usize = 40;
+/// The terminal node's id in the output. `end` itself is a keyword in Mermaid.
+const END_ID: &str = "node_end";
+
+/// Renders a dialogue as a graph: one box per node, labelled with its id and first
+/// text, and one edge per choice, labelled with its text, condition and actions.
+pub fn dialogue_graph(dialogue: &Dialogue, format: GraphFormat) -> String {
+    let graph = Graph::new(dialogue);
+    match format {
+        GraphFormat::Dot => graph.to_dot(),
+        GraphFormat::Mermaid => graph.to_mermaid(),
+    }
+}
+
+struct Graph<'a> {
+    dialogue: &'a Dialogue,
+    // Node ids can contain anything, so the output uses generated ones.
+    ids: HashMap<&'a str, String>,
+    missing: Vec<&'a str>,
+}
+
+impl<'a> Graph<'a> {
+    fn new(dialogue: &'a Dialogue) -> Self {
+        let mut ids = HashMap::new();
+        for (index, node) in dialogue.nodes.iter().enumerate() {
+            ids.insert(node.id.as_str(), format!("n{index}"));
+        }
+        ids.insert(END_NODE, END_ID.to_string());
+        let mut missing = vec![];
+        for choice in dialogue.nodes.iter().flat_map(|n| n.choices.iter()) {
+            if !ids.contains_key(choice.next.as_str()) {
+                ids.insert(choice.next.as_str(), format!("missing{}", missing.len()));
+                missing.push(choice.next.as_str());
+            }
+        }
+        Self {
+            dialogue,
+            ids,
+            missing,
+        }
+    }
+
+    fn edges(&self) -> impl Iterator<Item = (&str, &str, &'a Choice)> {
+        self.dialogue.nodes.iter().flat_map(move |node| {
+            node.choices.iter().map(move |c| {
+                (
+                    self.ids[node.id.as_str()].as_str(),
+                    self.ids[c.next.as_str()].as_str(),
+                    c,
+                )
+            })
+        })
+    }
+
+    fn ends(&self) -> bool {
+        self.dialogue
+            .nodes
+            .iter()
+            .flat_map(|n| n.choices.iter())
+            .any(|c| c.next == END_NODE)
+    }
+
+    fn to_dot(&self) -> String {
+        let mut out = String::from("digraph dialogue {\n    node [shape=box];\n");
+        for node in self.dialogue.nodes.iter() {
+            let label = node_label(&node.id, node.texts.first().map(|t| t.text.as_str()));
+            let style = if node.id == self.dialogue.entry {
+                ", penwidth=2"
+            } else {
+                ""
+            };
+            let _ = writeln!(
+                out,
+                "    {} [label=\"{}\"{}];",
+                self.ids[node.id.as_str()],
+                escape_dot(&label),
+                style
+            );
+        }
+        for id in self.missing.iter() {
+            let _ = writeln!(
+                out,
+                "    {} [label=\"{}\\n(missing)\", style=dashed];",
+                self.ids[id],
+                escape_dot(id)
+            );
+        }
+        if self.ends() {
+            let _ = writeln!(
+                out,
+                "    {END_ID} [label=\"{END_NODE}\", shape=doublecircle];"
+            );
+        }
+        for (from, to, choice) in self.edges() {
+            let _ = writeln!(
+                out,
+                "    {} -> {} [label=\"{}\"];",
+                from,
+                to,
+                escape_dot(&choice_label(choice))
+            );
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    fn to_mermaid(&self) -> String {
+        let mut out = String::from("flowchart TD\n");
+        for node in self.dialogue.nodes.iter() {
+            let label = node_label(&node.id, node.texts.first().map(|t| t.text.as_str()));
+            let _ = writeln!(
+                out,
+                "    {}[\"{}\"]",
+                self.ids[node.id.as_str()],
+                escape_mermaid(&label)
+            );
+        }
+        for id in self.missing.iter() {
+            let _ = writeln!(
+                out,
+                "    {}[\"{} (missing)\"]",
+                self.ids[id],
+                escape_mermaid(id)
+            );
+        }
+        if self.ends() {
+            let _ = writeln!(out, "    {END_ID}((\"{END_NODE}\"))");
+        }
+        for (from, to, choice) in self.edges() {
+            let _ = writeln!(
+                out,
+                "    {} -->|\"{}\"| {}",
+                from,
+                escape_mermaid(&choice_label(choice)),
+                to
+            );
+        }
+        if let Some(entry) = self.ids.get(self.dialogue.entry.as_str()) {
+            let _ = writeln!(out, "    style {entry} stroke-width:3px");
+        }
+        for id in self.missing.iter() {
+            let _ = writeln!(out, "    style {} stroke-dasharray:5", self.ids[id]);
+        }
+        out
+    }
+}
+
+fn node_label(id: &str, text: Option<&str>) -> String {
+    match text {
+        Some(text) => format!("{id}\n{}", truncate(text)),
+        None => id.to_string(),
+    }
+}
+
+fn choice_label(choice: &Choice) -> String {
+    let mut label = truncate(&choice.text);
+    if let Some(condition) = &choice.condition {
+        let _ = write!(label, "\n[{condition}]");
+    }
+    let actions = choice.actions.iter().flatten().map(|a| a.to_string());
+    let actions = actions.collect::<Vec<_>>().join(", ");
+    if !actions.is_empty() {
+        let _ = write!(label, "\n/ {actions}");
+    }
+    label
+}
+
+fn truncate(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() > MAX_LABEL {
+        let mut short = text.chars().take(MAX_LABEL - 1).collect::<String>();
+        short.push('…');
+        short
+    } else {
+        text
+    }
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn escape_mermaid(text: &str) -> String {
+    text.replace('"', "#quot;").replace('\n', "<br/>")
+}
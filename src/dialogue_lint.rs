@@ -0,0 +1,443 @@
+use crate::dialogue_actions::ActionRegistry;
+use crate::dialogue_template::{BUILTIN_PLACEHOLDERS, FLAG_PREFIX, placeholders};
+use crate::localization::{FALLBACK_LOCALE, Localization, dialogue_keys, string_key};
+use crate::story_system::{Dialogue, END_NODE, GameFlags};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Something wrong with a dialogue file, found without running the game.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintIssue {
+    pub file: PathBuf,
+    pub node: Option<String>,
+    pub message: String,
+    pub severity: Severity,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Severity {
+    /// The dialogue is broken and the lint fails.
+    Error,
+    /// Possibly fine, such as an action the game may register that the linter
+    /// doesn't know.
+    Warning,
+}
+
+impl LintIssue {
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl Display for LintIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.severity == Severity::Warning {
+            write!(f, "warning: ")?;
+        }
+        match &self.node {
+            Some(node) => write!(
+                f,
+                "{}: node `{}`: {}",
+                self.file.display(),
+                node,
+                self.message
+            ),
+            None => write!(f, "{}: {}", self.file.display(), self.message),
+        }
+    }
+}
+
+/// Loads every `.json` file in `dir` and lints them together, since flags read in
+/// one dialogue are often set by another. String keys are checked against the
+/// tables in the `locale` directory next to `dir`. Actions `registry` doesn't know
+/// are only warned about, since the game may register them.
+pub fn lint_dialogue_dir(dir: &Path, registry: &ActionRegistry) -> Vec<LintIssue> {
+    let mut issues = vec![];
+    let mut paths = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+            .collect::<Vec<_>>(),
+        Err(e) => {
+            issues.push(LintIssue {
+                file: dir.to_path_buf(),
+                node: None,
+                message: format!("couldn't read directory: {e}"),
+                severity: Severity::Error,
+            });
+            return issues;
+        }
+    };
+    paths.sort();
+
+    let mut dialogues = vec![];
+    for path in paths {
+        match read_dialogue(&path) {
+            Ok(dialogue) => dialogues.push((path, dialogue)),
+            Err(message) => issues.push(LintIssue {
+                file: path,
+                node: None,
+                message,
+                severity: Severity::Error,
+            }),
+        }
+    }
+    issues.extend(lint_actions(&dialogues, registry));
+    issues.extend(lint_dialogues(&dialogues));
+
+    let locale_dir = dir.parent().unwrap_or(dir).join("locale");
+    let strings = if locale_dir.is_dir() {
+        match Localization::load(&locale_dir) {
+            Ok(strings) => strings,
+            Err(message) => {
+                issues.push(LintIssue {
+                    file: locale_dir,
+                    node: None,
+                    message,
+                    severity: Severity::Error,
+                });
+                return issues;
+            }
+        }
+    } else {
+        Localization::default()
+    };
+    issues.extend(lint_strings(&dialogues, &strings));
+    issues.extend(lint_templates(&dialogues, &strings));
+    issues.extend(lint_speakers(&dialogues, dir.parent().unwrap_or(dir)));
+    issues
+}
+
+/// Reads a dialogue and checks its actions against `registry`, as the game does.
+pub fn load_dialogue(path: &Path, registry: &ActionRegistry) -> Result<Dialogue, String> {
+    let dialogue = read_dialogue(path)?;
+    dialogue
+        .validate_actions(registry)
+        .map_err(|e| e.to_string())?;
+    Ok(dialogue)
+}
+
+/// Reads a dialogue without checking its actions.
+pub fn read_dialogue(path: &Path) -> Result<Dialogue, String> {
+    let json = fs::read_to_string(path).map_err(|e| format!("couldn't read file: {e}"))?;
+    serde_json::from_str(&json).map_err(|e| e.to_string())
+}
+
+/// Reports actions whose arguments don't match what `registry` has for them, and
+/// warns about actions it doesn't have at all and about registered actions listed
+/// before built-in ones, which run after them anyway.
+pub fn lint_actions(
+    dialogues: &[(PathBuf, Dialogue)],
+    registry: &ActionRegistry,
+) -> Vec<LintIssue> {
+    let mut issues = vec![];
+    for (file, dialogue) in dialogues {
+        for node in dialogue.nodes.iter() {
+            let issue = |message: String, severity| LintIssue {
+                file: file.clone(),
+                node: Some(node.id.clone()),
+                message,
+                severity,
+            };
+            for action in node.actions() {
+                let Err(message) = registry.validate(action) else {
+                    continue;
+                };
+                let severity = if registry.contains(&action.name) {
+                    Severity::Error
+                } else {
+                    Severity::Warning
+                };
+                issues.push(issue(message, severity));
+            }
+            let lists = [&node.on_enter, &node.on_exit]
+                .into_iter()
+                .chain(node.choices.iter().map(|c| &c.actions))
+                .flatten();
+            for actions in lists {
+                let queued = actions
+                    .iter()
+                    .position(|a| registry.contains(&a.name) && !registry.is_built_in(&a.name));
+                let Some(queued) = queued else {
+                    continue;
+                };
+                if actions[queued..]
+                    .iter()
+                    .any(|a| registry.is_built_in(&a.name))
+                {
+                    let message = format!(
+                        "`{}` runs after the built-in actions listed after it",
+                        actions[queued]
+                    );
+                    issues.push(issue(message, Severity::Warning));
+                }
+            }
+        }
+    }
+    issues
+}
+
+/// Reports missing `next` and `resume` targets, nodes unreachable from `entry`, nodes
+/// the conversation can't end from, and flags or variables that are read by a
+/// condition but never set.
+pub fn lint_dialogues(dialogues: &[(PathBuf, Dialogue)]) -> Vec<LintIssue> {
+    let mut issues = vec![];
+    let written = written_variables(dialogues);
+    let mut read = HashSet::new();
+
+    for (file, dialogue) in dialogues {
+        let issue = |node: Option<&str>, message: String| LintIssue {
+            file: file.clone(),
+            node: node.map(|n| n.to_string()),
+            message,
+            severity: Severity::Error,
+        };
+        let mut nodes = HashMap::new();
+        for node in dialogue.nodes.iter() {
+            if nodes.insert(node.id.as_str(), node).is_some() {
+                issues.push(issue(Some(&node.id), "duplicate node id".to_string()));
+            }
+        }
+
+        if !nodes.contains_key(dialogue.entry.as_str()) {
+            issues.push(issue(
+                None,
+                format!("entry node `{}` doesn't exist", dialogue.entry),
+            ));
+        }
+
+        for node in dialogue.nodes.iter() {
+            for choice in node.choices.iter() {
+                if choice.next != END_NODE && !nodes.contains_key(choice.next.as_str()) {
+                    issues.push(issue(
+                        Some(&node.id),
+                        format!(
+                            "choice `{}` leads to missing node `{}`",
+                            choice.text, choice.next
+                        ),
+                    ));
+                }
+            }
+
+            let conditions = node
+                .texts
+                .iter()
+                .filter_map(|t| t.condition.as_ref())
+                .chain(node.choices.iter().filter_map(|c| c.condition.as_ref()));
+            for condition in conditions {
+                for name in condition.variables() {
+                    read.insert((file, node.id.as_str(), name));
+                }
+            }
+
+            for action in node.actions() {
+                let args = action.args(1);
+                let target = args.str(0);
+                if action.name == "resume" && !nodes.contains_key(target) {
+                    issues.push(issue(
+                        Some(&node.id),
+                        format!("`{action}` resumes at missing node `{target}`"),
+                    ));
+                }
+            }
+        }
+
+        let mut reachable = HashSet::new();
+        let mut queue = VecDeque::from([dialogue.entry.as_str()]);
+        while let Some(id) = queue.pop_front() {
+            if !reachable.insert(id) {
+                continue;
+            }
+            if let Some(node) = nodes.get(id) {
+                queue.extend(node.choices.iter().map(|c| c.next.as_str()));
+            }
+        }
+        for node in dialogue.nodes.iter() {
+            if !reachable.contains(node.id.as_str()) {
+                issues.push(issue(
+                    Some(&node.id),
+                    format!("unreachable from entry `{}`", dialogue.entry),
+                ));
+            }
+        }
+
+        // Walk back from `END_NODE` to find every node the conversation can end from.
+        let mut ending = HashSet::from([END_NODE]);
+        loop {
+            let before = ending.len();
+            for node in dialogue.nodes.iter() {
+                if node
+                    .choices
+                    .iter()
+                    .any(|c| ending.contains(c.next.as_str()))
+                {
+                    ending.insert(node.id.as_str());
+                }
+            }
+            if ending.len() == before {
+                break;
+            }
+        }
+        for node in dialogue.nodes.iter() {
+            if !ending.contains(node.id.as_str()) {
+                issues.push(issue(
+                    Some(&node.id),
+                    format!("dead end: `{END_NODE}` can't be reached from this node"),
+                ));
+            }
+        }
+    }
+
+    let mut read = read.into_iter().collect::<Vec<_>>();
+    read.sort();
+    for (file, node, name) in read {
+        if !written.contains(name) {
+            issues.push(LintIssue {
+                file: file.clone(),
+                node: Some(node.to_string()),
+                message: format!("`{name}` is read by a condition but never set"),
+                severity: Severity::Error,
+            });
+        }
+    }
+    issues
+}
+
+/// Reports string keys missing from English, which every other locale falls back
+/// to, and warns about keys other locales haven't translated yet.
+pub fn lint_strings(dialogues: &[(PathBuf, Dialogue)], strings: &Localization) -> Vec<LintIssue> {
+    let mut locales = strings.locales();
+    if !locales.contains(&FALLBACK_LOCALE) {
+        locales.insert(0, FALLBACK_LOCALE);
+    }
+    let mut issues = vec![];
+    for (file, dialogue) in dialogues {
+        let speaker_keys = dialogue
+            .all_speakers()
+            .into_iter()
+            .filter_map(|(_, s)| s.name.as_deref().and_then(string_key))
+            .map(|key| (None, key));
+        let node_keys = dialogue_keys(dialogue)
+            .into_iter()
+            .map(|(node, key)| (Some(node), key));
+        for (node, key) in speaker_keys.chain(node_keys) {
+            for locale in locales.iter() {
+                if !strings.contains(locale, key) {
+                    issues.push(LintIssue {
+                        file: file.clone(),
+                        node: node.map(|n| n.to_string()),
+                        message: format!("string `{key}` is missing from locale `{locale}`"),
+                        severity: if *locale == FALLBACK_LOCALE {
+                            Severity::Error
+                        } else {
+                            Severity::Warning
+                        },
+                    });
+                }
+            }
+        }
+    }
+    issues
+}
+
+/// Reports speakers whose accent isn't a colour, or whose portrait or font isn't a
+/// file under `assets_dir`.
+pub fn lint_speakers(dialogues: &[(PathBuf, Dialogue)], assets_dir: &Path) -> Vec<LintIssue> {
+    let mut issues = vec![];
+    for (file, dialogue) in dialogues {
+        for (id, speaker) in dialogue.all_speakers() {
+            let who = id.map_or("the default speaker".to_string(), |id| {
+                format!("speaker `{id}`")
+            });
+            let mut problems = vec![];
+            if let Some(accent) = speaker.accent.as_deref()
+                && speaker.accent_color().is_none()
+            {
+                problems.push(format!("{who} has an invalid accent `{accent}`"));
+            }
+            let files = [("portrait", &speaker.portrait), ("font", &speaker.font)];
+            for (what, path) in files {
+                if let Some(path) = path
+                    && !assets_dir.join(path).is_file()
+                {
+                    problems.push(format!("{who}'s {what} `{path}` doesn't exist"));
+                }
+            }
+            issues.extend(problems.into_iter().map(|message| LintIssue {
+                file: file.clone(),
+                node: None,
+                message,
+                severity: Severity::Error,
+            }));
+        }
+    }
+    issues
+}
+
+/// Every flag or variable some action sets, plus the variables a new game starts with.
+fn written_variables(dialogues: &[(PathBuf, Dialogue)]) -> HashSet<String> {
+    let mut written = GameFlags::default()
+        .var_names()
+        .map(|name| name.to_string())
+        .collect::<HashSet<_>>();
+    for action in dialogues
+        .iter()
+        .flat_map(|(_, d)| d.nodes.iter())
+        .flat_map(|n| n.actions())
+    {
+        let args = match action.name.as_str() {
+            "set_flag" => action.args(1),
+            "set" | "add" | "sub" => action.args(2),
+            _ => continue,
+        };
+        written.insert(args.str(0).to_string());
+    }
+    written
+}
+
+/// Reports text, choice and hint templates that don't parse or use placeholders nothing
+/// sets. Keys are checked in every locale's version of the string.
+pub fn lint_templates(dialogues: &[(PathBuf, Dialogue)], strings: &Localization) -> Vec<LintIssue> {
+    let written = written_variables(dialogues);
+    let mut issues = vec![];
+    for (file, dialogue) in dialogues {
+        for node in dialogue.nodes.iter() {
+            for text in node.strings() {
+                let versions = match string_key(text) {
+                    Some(key) => strings
+                        .locales()
+                        .into_iter()
+                        .filter_map(|locale| strings.lookup(locale, key))
+                        .collect(),
+                    None => vec![text],
+                };
+                for version in versions {
+                    let problem = match placeholders(version) {
+                        Err(e) => Some(e.to_string()),
+                        Ok(names) => names
+                            .into_iter()
+                            .find(|name| {
+                                !BUILTIN_PLACEHOLDERS.contains(name)
+                                    && !name.starts_with(FLAG_PREFIX)
+                                    && !written.contains(*name)
+                            })
+                            .map(|name| {
+                                format!("placeholder `{{{name}}}` in {version:?} is never set")
+                            }),
+                    };
+                    if let Some(message) = problem {
+                        issues.push(LintIssue {
+                            file: file.clone(),
+                            node: Some(node.id.clone()),
+                            message,
+                            severity: Severity::Error,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    issues
+}
@@ -0,0 +1,316 @@
+use crate::dialogue_actions::{Action, perform_action};
+use crate::dialogue_template::{TemplateContext, TemplateError, render};
+use crate::localization::Localization;
+use crate::story_system::{Choice, Dialogue, END_NODE, GameFlags, Speaker, Text};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+
+/// Steps through a [`Dialogue`] without a window or a Bevy `World`, so quests can be
+/// played from tests. Built-in actions change `flags` directly and `resume` sets
+/// `resume`; any other action is collected in `pending` for the caller to check or
+/// run, after the built-in ones as in the game.
+pub struct DialogueRunner<'a> {
+    dialogue: &'a Dialogue,
+    strings: Option<&'a Localization>,
+    speaker: Option<&'a str>,
+    node_id: String,
+    pub flags: GameFlags,
+    pub pending: Vec<Action>,
+    /// Where the next hail starts instead of `entry`, set by the `resume:<node>` action.
+    pub resume: Option<String>,
+    /// Nodes this conversation has left, so `once` nodes don't apply their actions again.
+    pub visited: HashSet<String>,
+    text_index: Option<usize>,
+    last_text: HashMap<String, usize>,
+    rng: StdRng,
+}
+
+impl<'a> DialogueRunner<'a> {
+    /// Starts at the dialogue's `entry` node and applies its `on_enter` actions.
+    pub fn start(dialogue: &'a Dialogue, flags: GameFlags) -> Self {
+        Self::start_seeded(dialogue, flags, 0)
+    }
+    /// Like `start`, with `seed` deciding which texts random nodes show.
+    pub fn start_seeded(dialogue: &'a Dialogue, flags: GameFlags, seed: u64) -> Self {
+        Self::resume_seeded(dialogue, flags, seed, None)
+    }
+    /// Like `start_seeded`, but starts at `resume` if an earlier conversation set
+    /// one, as hailing the same body again does in the game.
+    pub fn resume_seeded(
+        dialogue: &'a Dialogue,
+        flags: GameFlags,
+        seed: u64,
+        resume: Option<String>,
+    ) -> Self {
+        let node_id = resume.clone().unwrap_or_else(|| dialogue.entry.clone());
+        let mut runner = Self {
+            dialogue,
+            strings: None,
+            speaker: None,
+            node_id: node_id.clone(),
+            flags,
+            pending: vec![],
+            resume,
+            visited: HashSet::new(),
+            text_index: None,
+            last_text: HashMap::new(),
+            rng: StdRng::seed_from_u64(seed),
+        };
+        runner.enter(node_id);
+        runner
+    }
+    /// Resolves string keys in `text` and `show` through `strings`, rather than
+    /// returning them as written in the dialogue.
+    pub fn with_strings(mut self, strings: &'a Localization) -> Self {
+        self.strings = Some(strings);
+        self
+    }
+    /// The name `{speaker}` shows in templates when the text's speaker has none.
+    pub fn with_speaker(mut self, speaker: &'a str) -> Self {
+        self.speaker = Some(speaker);
+        self
+    }
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+    /// Whether a choice led to [`END_NODE`], closing the conversation.
+    pub fn is_finished(&self) -> bool {
+        self.node_id == END_NODE
+    }
+    /// The current text as the player sees it. Like the comms window, a template
+    /// that can't be filled in is shown as written.
+    pub fn text(&self) -> Option<String> {
+        self.raw_text().map(|text| {
+            self.show(text)
+                .unwrap_or_else(|_| self.localize(text).to_string())
+        })
+    }
+    /// Who says the current text.
+    pub fn speaker(&self) -> Option<&Speaker> {
+        self.dialogue.speaker_of(self.current_text()?)
+    }
+    fn current_text(&self) -> Option<&Text> {
+        self.text_index
+            .and_then(|index| self.dialogue.text_at(&self.node_id, index, &self.flags))
+            .or_else(|| self.dialogue.get_text(&self.node_id, &self.flags))
+    }
+    fn raw_text(&self) -> Option<&str> {
+        self.current_text().map(|t| t.text.as_str())
+    }
+    fn localize<'b>(&'b self, text: &'b str) -> &'b str {
+        match self.strings {
+            Some(strings) => strings.get(text),
+            None => text,
+        }
+    }
+    /// Localizes a dialogue string and fills in its placeholders. There are no ship
+    /// stats outside the game, so `{ship.*}` placeholders fail.
+    pub fn show(&self, text: &str) -> Result<String, TemplateError> {
+        let name = self.speaker().and_then(|s| s.name.as_deref());
+        let context = TemplateContext {
+            flags: &self.flags,
+            speaker: name.map(|name| self.localize(name)).or(self.speaker),
+            ship: None,
+        };
+        render(self.localize(text), &context)
+    }
+    /// The choices whose conditions pass with the current flags.
+    pub fn choices(&self) -> Vec<Choice> {
+        self.dialogue
+            .get_choices(&self.node_id, &self.flags)
+            .unwrap_or_default()
+    }
+    pub fn choose(&mut self, index: usize) -> Result<(), String> {
+        let choices = self.choices();
+        let Some(choice) = choices.get(index) else {
+            return Err(format!(
+                "node `{}` has {} visible choice(s), can't take choice {}",
+                self.node_id,
+                choices.len(),
+                index
+            ));
+        };
+        self.perform(choice.actions.iter().flatten());
+        self.exit();
+        self.enter(choice.next.clone());
+        Ok(())
+    }
+
+    fn enter(&mut self, node_id: String) {
+        self.node_id = node_id;
+        if let Some(node) = self.dialogue.node(&self.node_id)
+            && !(node.once && self.visited.contains(&node.id))
+        {
+            self.perform(node.on_enter.iter().flatten());
+        }
+        let previous = self.last_text.get(&self.node_id).copied();
+        self.text_index =
+            self.dialogue
+                .pick_text(&self.node_id, &self.flags, previous, &mut self.rng);
+        if let Some(index) = self.text_index {
+            self.last_text.insert(self.node_id.clone(), index);
+        }
+    }
+    fn exit(&mut self) {
+        if let Some(node) = self.dialogue.node(&self.node_id)
+            && !(node.once && self.visited.contains(&node.id))
+        {
+            self.perform(node.on_exit.iter().flatten());
+        }
+        self.visited.insert(self.node_id.clone());
+    }
+    fn perform<'b>(&mut self, actions: impl Iterator<Item = &'b Action>) {
+        for action in actions {
+            if action.name == "resume" {
+                self.resume = Some(action.args(1).str(0).to_string());
+            } else if !perform_action(action, &mut self.flags) {
+                self.pending.push(action.clone());
+            }
+        }
+        // There are no `FlagChanged` events outside the game to send them as.
+        self.flags.take_changes();
+    }
+}
+
+/// A recorded playthrough: which dialogues were hailed, which choices were taken,
+/// and what the player should have seen along the way.
+#[derive(Deserialize, Debug, Clone)]
+pub struct PlaythroughScript {
+    /// Decides which texts random nodes show, so a script always sees the same ones.
+    #[serde(default)]
+    pub seed: u64,
+    pub steps: Vec<ScriptStep>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptStep {
+    /// Hail the named dialogue, starting at its entry node, or where a `resume`
+    /// action in an earlier hail of it said to. The name is also what `{speaker}`
+    /// shows.
+    Start(String),
+    /// Take the visible choice at this index.
+    Choose(usize),
+    /// The current text must contain this string.
+    ExpectText(String),
+    /// The visible choices must be exactly these, in order.
+    ExpectChoices(Vec<String>),
+    ExpectNode(String),
+    /// Each flag must be set (`true`) or unset (`false`).
+    ExpectFlags(HashMap<String, bool>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptError {
+    pub step: usize,
+    pub message: String,
+}
+
+impl Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "step {}: {}", self.step, self.message)
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+impl PlaythroughScript {
+    /// Plays the script against `dialogues`, keyed by the names used in `start` steps,
+    /// stopping at the first expectation that doesn't hold. Text is compared after
+    /// resolving it through `strings`. Flags carry over between dialogues and are
+    /// written back to `flags` when the script succeeds.
+    pub fn run(
+        &self,
+        dialogues: &HashMap<String, Dialogue>,
+        strings: &Localization,
+        flags: &mut GameFlags,
+    ) -> Result<(), ScriptError> {
+        let mut runner: Option<DialogueRunner> = None;
+        let mut carried = flags.clone();
+        // Where each dialogue was last told to resume, by name.
+        let mut resume: HashMap<&str, String> = HashMap::new();
+        let mut hailed = "";
+        for (step, action) in self.steps.iter().enumerate() {
+            let error = |message: String| ScriptError { step, message };
+            if let ScriptStep::Start(name) = action {
+                let dialogue = dialogues
+                    .get(name)
+                    .ok_or_else(|| error(format!("no dialogue named `{name}`")))?;
+                if let Some(previous) = runner.take() {
+                    if let Some(node) = previous.resume {
+                        resume.insert(hailed, node);
+                    }
+                    carried = previous.flags;
+                }
+                hailed = name;
+                runner = Some(
+                    DialogueRunner::resume_seeded(
+                        dialogue,
+                        std::mem::take(&mut carried),
+                        self.seed.wrapping_add(step as u64),
+                        resume.get(name.as_str()).cloned(),
+                    )
+                    .with_strings(strings)
+                    .with_speaker(name),
+                );
+                continue;
+            }
+            let Some(runner) = runner.as_mut() else {
+                return Err(error("no dialogue started".to_string()));
+            };
+            match action {
+                ScriptStep::Start(_) => unreachable!(),
+                ScriptStep::Choose(index) => runner.choose(*index).map_err(error)?,
+                ScriptStep::ExpectText(expected) => {
+                    let text = match runner.raw_text() {
+                        Some(text) => runner.show(text).map_err(|e| error(e.to_string()))?,
+                        None => String::new(),
+                    };
+                    if !text.contains(expected.as_str()) {
+                        return Err(error(format!(
+                            "expected text containing {expected:?} in node `{}`, found {text:?}",
+                            runner.node_id()
+                        )));
+                    }
+                }
+                ScriptStep::ExpectChoices(expected) => {
+                    let choices = runner
+                        .choices()
+                        .iter()
+                        .map(|c| runner.show(&c.text))
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|e| error(e.to_string()))?;
+                    if &choices != expected {
+                        return Err(error(format!(
+                            "expected choices {expected:?} in node `{}`, found {choices:?}",
+                            runner.node_id()
+                        )));
+                    }
+                }
+                ScriptStep::ExpectNode(expected) => {
+                    if runner.node_id() != expected {
+                        return Err(error(format!(
+                            "expected node `{expected}`, found `{}`",
+                            runner.node_id()
+                        )));
+                    }
+                }
+                ScriptStep::ExpectFlags(expected) => {
+                    for (flag, set) in expected.iter() {
+                        if runner.flags.is_set(flag) != *set {
+                            return Err(error(format!(
+                                "expected flag `{flag}` to be {}",
+                                if *set { "set" } else { "unset" }
+                            )));
+                        }
+                    }
+                }
+            }
+        }
+        *flags = runner.map_or(carried, |r| r.flags);
+        Ok(())
+    }
+}
@@ -0,0 +1,153 @@
+use crate::story_system::GameFlags;
+use bevy::math::Vec2;
+use std::fmt::Display;
+use std::ops::Range;
+
+/// Placeholders every template can use besides game variables.
+pub const BUILTIN_PLACEHOLDERS: &[&str] =
+    &["speaker", "ship.speed", "ship.heading", "ship.x", "ship.y"];
+
+/// Placeholders starting with this read a flag and show `true` or `false`.
+pub const FLAG_PREFIX: &str = "flag.";
+
+/// What a dialogue line can refer to when it's shown.
+pub struct TemplateContext<'a> {
+    pub flags: &'a GameFlags,
+    /// The name of the `SolarBody` being talked to.
+    pub speaker: Option<&'a str>,
+    pub ship: Option<ShipStats>,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct ShipStats {
+    pub speed: f32,
+    /// Radians, as stored in `BackgroundStarConfig::direction`.
+    pub direction: f32,
+    pub position: Vec2,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateError {
+    pub source: String,
+    pub message: String,
+    pub span: Range<usize>,
+}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid template `{}`: {} at {}..{}",
+            self.source, self.message, self.span.start, self.span.end
+        )
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+enum Part<'a> {
+    Text(&'a str),
+    Placeholder(&'a str, Range<usize>),
+}
+
+/// Splits dialogue text such as `Your reward is {credits} credits, {player_name}`
+/// into text and placeholders. `{{` and `}}` stand for literal braces.
+fn parse(source: &str) -> Result<Vec<Part<'_>>, TemplateError> {
+    let error = |message: &str, span: Range<usize>| TemplateError {
+        source: source.to_string(),
+        message: message.to_string(),
+        span,
+    };
+    let mut parts = vec![];
+    let mut start = 0;
+    let mut chars = source.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '{' | '}' if chars.peek().is_some_and(|(_, next)| *next == c) => {
+                parts.push(Part::Text(&source[start..=i]));
+                chars.next();
+                start = i + 2;
+            }
+            '{' => {
+                parts.push(Part::Text(&source[start..i]));
+                let Some(end) = source[i..].find('}').map(|end| i + end) else {
+                    return Err(error("unclosed `{`", i..source.len()));
+                };
+                let name = source[i + 1..end].trim();
+                if name.is_empty() {
+                    return Err(error("empty placeholder", i..end + 1));
+                }
+                if !name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '.')
+                {
+                    return Err(error(
+                        "placeholder names can only use letters, digits, `_` and `.`",
+                        i..end + 1,
+                    ));
+                }
+                parts.push(Part::Placeholder(name, i..end + 1));
+                while chars.peek().is_some_and(|(j, _)| *j <= end) {
+                    chars.next();
+                }
+                start = end + 1;
+            }
+            '}' => return Err(error("unmatched `}`, write `}}` for a brace", i..i + 1)),
+            _ => {}
+        }
+    }
+    parts.push(Part::Text(&source[start..]));
+    Ok(parts)
+}
+
+/// The placeholder names used in `source`, checking its syntax.
+pub fn placeholders(source: &str) -> Result<Vec<&str>, TemplateError> {
+    Ok(parse(source)?
+        .into_iter()
+        .filter_map(|part| match part {
+            Part::Placeholder(name, _) => Some(name),
+            Part::Text(_) => None,
+        })
+        .collect())
+}
+
+/// Fills in every placeholder in `source`, failing on names that aren't built in,
+/// aren't `flag.` lookups and aren't set as variables.
+pub fn render(source: &str, context: &TemplateContext) -> Result<String, TemplateError> {
+    let mut out = String::with_capacity(source.len());
+    for part in parse(source)? {
+        match part {
+            Part::Text(text) => out.push_str(text),
+            Part::Placeholder(name, span) => match context.lookup(name) {
+                Some(value) => out.push_str(&value),
+                None => {
+                    return Err(TemplateError {
+                        source: source.to_string(),
+                        message: format!("unknown placeholder `{name}`"),
+                        span,
+                    });
+                }
+            },
+        }
+    }
+    Ok(out)
+}
+
+impl TemplateContext<'_> {
+    fn lookup(&self, name: &str) -> Option<String> {
+        if let Some(flag) = name.strip_prefix(FLAG_PREFIX) {
+            return Some(self.flags.is_set(flag).to_string());
+        }
+        let ship = self.ship.as_ref();
+        match name {
+            "speaker" => self.speaker.map(|s| s.to_string()),
+            "ship.speed" => ship.map(|s| format!("{:.1}", s.speed)),
+            "ship.heading" => {
+                ship.map(|s| format!("{:.0}", s.direction.to_degrees().rem_euclid(360.0)))
+            }
+            "ship.x" => ship.map(|s| format!("{:.0}", s.position.x)),
+            "ship.y" => ship.map(|s| format!("{:.0}", s.position.y)),
+            _ => self.flags.var(name).map(|v| v.to_string()),
+        }
+    }
+}
@@ -0,0 +1,582 @@
+use crate::GameActions;
+use crate::input_actions::ActionState;
+use crate::player_ship::MyShip;
+use crate::save_system::{
+    DialogueProgress, apply_progress, capture_progress, forget_missing_nodes,
+};
+use crate::solar_system::{
+    DEFAULT_SYSTEM, SolarBody, SolarBodyDescriptor, SolarSystem, spawn_solar_system,
+};
+use crate::space_position::SpacePosition;
+use crate::story_system::{ActiveDialogue, Dialogue, DialogueHandle, GameFlags};
+use crate::system_generator::generate_system;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoadFailedEvent, AssetLoader, LoadContext, LoadState};
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+
+pub struct GalaxyPlugin;
+impl Plugin for GalaxyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Galaxy>();
+        app.init_asset_loader::<GalaxyLoader>();
+        // Stands in until the galaxy file loads, and for good if it can't be.
+        app.insert_resource(GalaxyMap::new(Galaxy::single(DEFAULT_SYSTEM)));
+        app.add_event::<JumpTo>();
+        app.add_systems(Startup, load_galaxy);
+        app.add_systems(
+            Update,
+            (
+                (update_galaxy, control_galaxy_map, jump)
+                    .chain()
+                    .before(spawn_solar_system),
+                (
+                    restore_progress.after(spawn_solar_system),
+                    update_galaxy_map,
+                )
+                    .chain(),
+            ),
+        );
+    }
+}
+
+/// The galaxy a new game starts in, under `assets/`.
+pub const GALAXY_FILE: &str = "main.galaxy.json";
+
+/// Ships drop out of a jump this far from the star, on the side facing the system
+/// they came from.
+pub const ARRIVAL_DISTANCE: f32 = 5000.0;
+
+/// The star systems a ship can fly between, as written in `assets/*.galaxy.json`.
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+#[serde(try_from = "GalaxyDef")]
+pub struct Galaxy {
+    /// The id of the system a new game starts in.
+    pub start: String,
+    pub systems: Vec<StarSystemEntry>,
+    /// Pairs of system ids a ship can jump between, either way.
+    pub lanes: Vec<[String; 2]>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StarSystemEntry {
+    pub id: String,
+    pub name: String,
+    /// Where the system is in the galaxy, in light years.
+    pub position: Vec2,
+    pub source: SystemSource,
+}
+
+/// Where a system's bodies come from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SystemSource {
+    /// A hand-made `.system.json` file under `assets/`.
+    File(String),
+    /// Made up by [`generate_system`] from this seed whenever the ship arrives.
+    Seed(u64),
+}
+
+#[derive(Deserialize)]
+struct GalaxyDef {
+    start: String,
+    systems: Vec<SystemDef>,
+    #[serde(default)]
+    lanes: Vec<[String; 2]>,
+}
+
+#[derive(Deserialize)]
+struct SystemDef {
+    id: String,
+    name: String,
+    position: [f32; 2],
+    #[serde(default)]
+    file: Option<String>,
+    #[serde(default)]
+    seed: Option<u64>,
+}
+
+impl TryFrom<GalaxyDef> for Galaxy {
+    type Error = String;
+    fn try_from(def: GalaxyDef) -> Result<Self, Self::Error> {
+        let mut ids = HashSet::new();
+        let mut systems = vec![];
+        for system in def.systems {
+            if !ids.insert(system.id.clone()) {
+                return Err(format!("two systems have the id `{}`", system.id));
+            }
+            let source = match (system.file, system.seed) {
+                (Some(file), None) => SystemSource::File(file),
+                (None, Some(seed)) => SystemSource::Seed(seed),
+                _ => {
+                    return Err(format!(
+                        "system `{}` needs either a `file` or a `seed`",
+                        system.id
+                    ));
+                }
+            };
+            systems.push(StarSystemEntry {
+                id: system.id,
+                name: system.name,
+                position: Vec2::from_array(system.position),
+                source,
+            });
+        }
+        if !ids.contains(&def.start) {
+            return Err(format!("the start system `{}` doesn't exist", def.start));
+        }
+        for [from, to] in def.lanes.iter() {
+            if let Some(missing) = [from, to].into_iter().find(|id| !ids.contains(*id)) {
+                return Err(format!(
+                    "lane from `{from}` to `{to}`: there's no system `{missing}`"
+                ));
+            }
+            if from == to {
+                return Err(format!("lane from `{from}` leads back to itself"));
+            }
+        }
+        Ok(Galaxy {
+            start: def.start,
+            systems,
+            lanes: def.lanes,
+        })
+    }
+}
+
+/// Loads `assets/*.galaxy.json`. The longer extension keeps them apart from
+/// dialogue files, which are plain `.json`.
+#[derive(Default)]
+pub struct GalaxyLoader;
+
+#[derive(Debug)]
+pub enum GalaxyLoadError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+}
+
+impl Display for GalaxyLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GalaxyLoadError::Io(e) => write!(f, "couldn't read galaxy: {e}"),
+            GalaxyLoadError::Json(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for GalaxyLoadError {}
+
+impl AssetLoader for GalaxyLoader {
+    type Asset = Galaxy;
+    type Settings = ();
+    type Error = GalaxyLoadError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Galaxy, GalaxyLoadError> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(GalaxyLoadError::Io)?;
+        serde_json::from_slice(&bytes).map_err(GalaxyLoadError::Json)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["galaxy.json"]
+    }
+}
+
+impl Galaxy {
+    /// A galaxy of just the system in `file`, with nowhere to jump to.
+    pub fn single(file: &str) -> Self {
+        Galaxy {
+            start: "home".to_string(),
+            systems: vec![StarSystemEntry {
+                id: "home".to_string(),
+                name: "Home".to_string(),
+                position: Vec2::ZERO,
+                source: SystemSource::File(file.to_string()),
+            }],
+            lanes: vec![],
+        }
+    }
+    pub fn system(&self, id: &str) -> Option<&StarSystemEntry> {
+        self.systems.iter().find(|system| system.id == id)
+    }
+    /// The systems one jump from `id`, by name.
+    pub fn neighbours(&self, id: &str) -> Vec<&StarSystemEntry> {
+        let mut neighbours: Vec<_> = self
+            .lanes
+            .iter()
+            .filter_map(|[from, to]| {
+                if from == id {
+                    self.system(to)
+                } else if to == id {
+                    self.system(from)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        neighbours.sort_by(|a, b| a.name.cmp(&b.name));
+        neighbours.dedup_by(|a, b| a.id == b.id);
+        neighbours
+    }
+}
+
+impl StarSystemEntry {
+    /// Loads the system's file, or generates it afresh from its seed.
+    pub fn load(
+        &self,
+        asset_server: &AssetServer,
+        systems: &mut Assets<SolarBodyDescriptor>,
+    ) -> Handle<SolarBodyDescriptor> {
+        match &self.source {
+            SystemSource::File(file) => asset_server.load(file.clone()),
+            SystemSource::Seed(seed) => systems.add(generate_system(*seed)),
+        }
+    }
+}
+
+/// Where the ship is in the galaxy, and the galaxy map.
+#[derive(Resource)]
+pub struct GalaxyMap {
+    pub galaxy: Galaxy,
+    /// The galaxy file `galaxy` was loaded from. Edits to it replace `galaxy`.
+    pub handle: Handle<Galaxy>,
+    /// The id of the system the ship is in.
+    pub current: String,
+    /// Which of the current system's neighbours the map has picked out to jump to.
+    pub target: usize,
+    pub open: bool,
+    /// Dialogue progress in systems the ship has left, by system id and then body
+    /// name, put back when it returns.
+    pub progress: HashMap<String, HashMap<String, DialogueProgress>>,
+}
+
+impl GalaxyMap {
+    pub fn new(galaxy: Galaxy) -> Self {
+        GalaxyMap {
+            current: galaxy.start.clone(),
+            galaxy,
+            handle: Handle::default(),
+            target: 0,
+            open: false,
+            progress: HashMap::new(),
+        }
+    }
+    /// The system the next jump goes to, if there's a lane out of this one.
+    pub fn target(&self) -> Option<&StarSystemEntry> {
+        let neighbours = self.galaxy.neighbours(&self.current);
+        neighbours
+            .get(self.target % neighbours.len().max(1))
+            .copied()
+    }
+}
+
+/// Jumps the ship to another system, unloading this one's bodies and loading that
+/// one's. Flags, the ship's heading and speed, and the clock carry over.
+#[derive(Event, Debug, Clone)]
+pub struct JumpTo {
+    pub system: String,
+    /// Where the ship ends up; by default it arrives from the direction of the
+    /// system it left.
+    pub arrival: Option<Vec2>,
+}
+
+fn load_galaxy(mut galaxy: ResMut<GalaxyMap>, asset_server: Res<AssetServer>) {
+    galaxy.handle = asset_server.load(GALAXY_FILE);
+}
+
+/// Takes the galaxy once its file has loaded and starts the game in its start
+/// system, or in the stand-in if it couldn't be loaded. Later edits to the file
+/// replace the galaxy without moving the ship.
+fn update_galaxy(
+    mut events: EventReader<AssetEvent<Galaxy>>,
+    mut failures: EventReader<AssetLoadFailedEvent<Galaxy>>,
+    mut entered: Local<bool>,
+    galaxies: Res<Assets<Galaxy>>,
+    mut galaxy: ResMut<GalaxyMap>,
+    mut solar_system: ResMut<SolarSystem>,
+    (asset_server, mut systems): (Res<AssetServer>, ResMut<Assets<SolarBodyDescriptor>>),
+) {
+    let id = galaxy.handle.id();
+    let mut failed = false;
+    for failure in failures.read().filter(|failure| failure.id == id) {
+        warn!("Couldn't load the galaxy: {}", failure.error);
+        failed = true;
+    }
+    let changed = events
+        .read()
+        .any(|event| event.is_loaded_with_dependencies(id) || event.is_modified(id));
+    if let Some(loaded) = galaxies.get(id).filter(|_| changed) {
+        galaxy.galaxy = loaded.clone();
+        galaxy.target = 0;
+    } else if !failed {
+        return;
+    }
+    if !*entered {
+        *entered = true;
+        galaxy.current = galaxy.galaxy.start.clone();
+        if let Some(start) = galaxy.galaxy.system(&galaxy.current) {
+            solar_system.handle = start.load(&asset_server, &mut systems);
+        }
+    }
+}
+
+fn control_galaxy_map(
+    mut commands: Commands,
+    mut galaxy: ResMut<GalaxyMap>,
+    actions: Res<ActionState<GameActions>>,
+) {
+    use GameActions::*;
+    if actions.just_pressed(ToggleGalaxyMap) {
+        galaxy.open = !galaxy.open;
+    }
+    if !galaxy.open {
+        return;
+    }
+    if actions.just_pressed(NextJumpTarget) {
+        let count = galaxy.galaxy.neighbours(&galaxy.current).len().max(1);
+        galaxy.target = (galaxy.target + 1) % count;
+    }
+    if actions.just_pressed(Jump) {
+        if let Some(target) = galaxy.target() {
+            commands.send_event(JumpTo {
+                system: target.id.clone(),
+                arrival: None,
+            });
+        }
+        galaxy.open = false;
+    }
+}
+
+/// Ends any conversation, puts the current system's dialogue progress aside and
+/// despawns its bodies, then sets the next system loading for `spawn_solar_system`.
+fn jump(
+    mut commands: Commands,
+    mut events: EventReader<JumpTo>,
+    (mut galaxy, mut solar_system): (ResMut<GalaxyMap>, ResMut<SolarSystem>),
+    (asset_server, mut systems): (Res<AssetServer>, ResMut<Assets<SolarBodyDescriptor>>),
+    (mut flags, mut active_dialogue): (ResMut<GameFlags>, ResMut<ActiveDialogue>),
+    bodies: Query<(Entity, &SolarBody)>,
+    mut ship: Single<&mut SpacePosition, With<MyShip>>,
+) {
+    for JumpTo { system, arrival } in events.read() {
+        if *system == galaxy.current {
+            continue;
+        }
+        let Some(to) = galaxy.galaxy.system(system).cloned() else {
+            warn!("Can't jump to `{system}`: there's no such system");
+            continue;
+        };
+        let from = galaxy
+            .galaxy
+            .system(&galaxy.current)
+            .map_or(Vec2::ZERO, |from| from.position);
+
+        active_dialogue.end(&mut flags, &mut commands);
+        let names = bodies
+            .iter()
+            .map(|(entity, body)| (entity, body.name.clone()))
+            .collect();
+        let left = capture_progress(&active_dialogue, &names);
+        let current = galaxy.current.clone();
+        // Extended rather than replaced, so a game loaded just before the jump keeps
+        // the progress it saved here.
+        galaxy.progress.entry(current).or_default().extend(left);
+        active_dialogue.node_id.clear();
+        active_dialogue.resume.clear();
+        active_dialogue.visited.clear();
+        active_dialogue.last_text.clear();
+        for (entity, _) in bodies.iter() {
+            commands.entity(entity).despawn();
+        }
+
+        ship.0 = arrival
+            .unwrap_or_else(|| (from - to.position).normalize_or(Vec2::X) * ARRIVAL_DISTANCE);
+        solar_system.handle = to.load(&asset_server, &mut systems);
+        solar_system.spawned = None;
+        galaxy.current = to.id.clone();
+        galaxy.target = 0;
+        info!("Jumped to {}", to.name);
+    }
+}
+
+/// Gives the bodies of the system the ship has just arrived in back the progress
+/// they had when it left, once they and their dialogues have loaded. Progress in
+/// nodes their dialogues no longer have, as from an old save, is dropped.
+fn restore_progress(
+    mut galaxy: ResMut<GalaxyMap>,
+    solar_system: Res<SolarSystem>,
+    mut active_dialogue: ResMut<ActiveDialogue>,
+    bodies: Query<(Entity, &SolarBody, Option<&DialogueHandle>)>,
+    (asset_server, dialogues): (Res<AssetServer>, Res<Assets<Dialogue>>),
+) {
+    if solar_system.spawned != Some(solar_system.handle.id())
+        || !galaxy.progress.contains_key(&galaxy.current)
+    {
+        return;
+    }
+    let loading = bodies
+        .iter()
+        .filter_map(|(_, _, handle)| handle)
+        .any(|handle| {
+            matches!(
+                asset_server.get_load_state(&handle.0),
+                Some(LoadState::Loading | LoadState::NotLoaded)
+            )
+        });
+    if loading {
+        return;
+    }
+    let current = galaxy.current.clone();
+    let mut progress = galaxy.progress.remove(&current).unwrap_or_default();
+    let loaded = bodies
+        .iter()
+        .filter_map(|(_, body, handle)| {
+            let dialogue = dialogues.get(&handle?.0)?;
+            Some((body.name.clone(), dialogue))
+        })
+        .collect();
+    forget_missing_nodes(&mut progress, &loaded);
+    let names = bodies
+        .iter()
+        .map(|(entity, body, _)| (body.name.clone(), entity))
+        .collect();
+    apply_progress(progress, &mut active_dialogue, &names);
+}
+
+#[derive(Component)]
+struct GalaxyMapView;
+
+const MAP_MARGIN: f32 = 160.0;
+const MAP_BACKGROUND: Color = Color::srgba(0.0, 0.0, 0.05, 0.9);
+const STAR_SIZE: f32 = 10.0;
+const LANE_DOT_SIZE: f32 = 3.0;
+const LANE_DOT_SPACING: f32 = 12.0;
+const LANE_COLOR: Color = Color::srgb(0.25, 0.3, 0.45);
+const ROUTE_COLOR: Color = Color::srgb(0.5, 0.6, 0.9);
+const STAR_COLOR: Color = Color::srgb(0.6, 0.6, 0.7);
+const CURRENT_COLOR: Color = Color::WHITE;
+const TARGET_COLOR: Color = Color::srgb(1.0, 0.85, 0.3);
+
+/// Rebuilds the map whenever it opens, closes or changes. Lanes are drawn as rows
+/// of dots, since the UI has no lines.
+fn update_galaxy_map(
+    mut commands: Commands,
+    galaxy: Res<GalaxyMap>,
+    views: Query<Entity, With<GalaxyMapView>>,
+    window: Single<&Window>,
+) {
+    if !galaxy.is_changed() {
+        return;
+    }
+    for view in views.iter() {
+        commands.entity(view).despawn_recursive();
+    }
+    if !galaxy.open {
+        return;
+    }
+    let target = galaxy.target();
+    let to_screen = map_projection(&galaxy.galaxy, window.size());
+    commands
+        .spawn((
+            GalaxyMapView,
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                padding: UiRect::all(Val::Px(24.0)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::SpaceBetween,
+                ..default()
+            },
+            BackgroundColor(MAP_BACKGROUND),
+            GlobalZIndex(1),
+        ))
+        .with_children(|map| {
+            for [from, to] in galaxy.galaxy.lanes.iter() {
+                let (Some(a), Some(b)) = (galaxy.galaxy.system(from), galaxy.galaxy.system(to))
+                else {
+                    continue;
+                };
+                let ends = [from, to];
+                let color = if !ends.contains(&&galaxy.current) {
+                    LANE_COLOR
+                } else if target.is_some_and(|target| ends.contains(&&target.id)) {
+                    TARGET_COLOR
+                } else {
+                    ROUTE_COLOR
+                };
+                let (start, end) = (to_screen(a.position), to_screen(b.position));
+                let dots = (start.distance(end) / LANE_DOT_SPACING).floor() as usize;
+                for dot in 1..dots {
+                    let at = start.lerp(end, dot as f32 / dots as f32);
+                    map.spawn(dot_node(at, LANE_DOT_SIZE, color));
+                }
+            }
+            for system in galaxy.galaxy.systems.iter() {
+                let at = to_screen(system.position);
+                let (color, label) = if system.id == galaxy.current {
+                    (CURRENT_COLOR, format!("{} (here)", system.name))
+                } else if target.is_some_and(|target| target.id == system.id) {
+                    (TARGET_COLOR, system.name.clone())
+                } else {
+                    (STAR_COLOR, system.name.clone())
+                };
+                map.spawn(dot_node(at, STAR_SIZE, color));
+                map.spawn((
+                    Text::new(label),
+                    TextFont::from_font_size(16.0),
+                    TextColor(color),
+                    Node {
+                        position_type: PositionType::Absolute,
+                        left: Val::Px(at.x + STAR_SIZE),
+                        top: Val::Px(at.y - STAR_SIZE),
+                        ..default()
+                    },
+                ));
+            }
+            map.spawn((Text::new("Galaxy map"), TextColor(CURRENT_COLOR)));
+            let hint = match target {
+                Some(target) => format!("Tab: next lane   J: jump to {}   M: close", target.name),
+                None => "No lanes lead out of this system   M: close".to_string(),
+            };
+            map.spawn((Text::new(hint), TextColor(STAR_COLOR)));
+        });
+}
+
+/// Fits the galaxy into the window, returning a function from galactic coordinates
+/// to UI pixels.
+fn map_projection(galaxy: &Galaxy, window: Vec2) -> impl Fn(Vec2) -> Vec2 {
+    let (min, max) = galaxy.systems.iter().fold(
+        (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+        |(min, max), system| (min.min(system.position), max.max(system.position)),
+    );
+    let middle = (min + max) / 2.0;
+    let span = (max - min).max(Vec2::splat(1.0));
+    let room = (window - Vec2::splat(MAP_MARGIN * 2.0)).max(Vec2::ONE);
+    let scale = (room / span).min_element();
+    // UI y runs down the screen, galactic y up it.
+    move |position| window / 2.0 + (position - middle) * scale * Vec2::new(1.0, -1.0)
+}
+
+fn dot_node(at: Vec2, size: f32, color: Color) -> impl Bundle {
+    (
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(at.x - size / 2.0),
+            top: Val::Px(at.y - size / 2.0),
+            width: Val::Px(size),
+            height: Val::Px(size),
+            ..default()
+        },
+        BorderRadius::MAX,
+        BackgroundColor(color),
+    )
+}
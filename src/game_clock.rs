@@ -0,0 +1,105 @@
+use crate::GameActions;
+use crate::input_actions::ActionState;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct GameClockPlugin;
+impl Plugin for GameClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameClock>();
+        // Time moves on before anything reads it, like Bevy's own clocks.
+        app.add_systems(First, advance_clock);
+        app.add_systems(Update, control_clock);
+    }
+}
+
+/// Game time moves in whole ticks of this many seconds, so the same ticks always
+/// put the planets in the same places whatever the frame rate.
+pub const TICK_SECONDS: f64 = 1.0 / 60.0;
+
+/// How many times faster than real time the game can run.
+pub const WARP_STEPS: &[u32] = &[1, 10, 100, 1000];
+
+/// Frames longer than this, such as while the window is dragged, only advance the
+/// game by this much real time.
+const MAX_FRAME_SECONDS: f64 = 0.25;
+
+/// Game time, which drives orbits and the ship. It can be paused and warped, and is
+/// saved with the game. Only `ticks` is saved; a loaded game runs at 1x.
+#[derive(Resource, Default, Debug, Clone, Serialize, Deserialize)]
+pub struct GameClock {
+    /// Ticks since the start of a new game.
+    pub ticks: u64,
+    #[serde(skip)]
+    warp: usize,
+    #[serde(skip)]
+    paused: bool,
+    /// Ticks taken this frame.
+    #[serde(skip)]
+    step: u64,
+    /// Warped real time not yet made up into a whole tick.
+    #[serde(skip)]
+    carry: f64,
+}
+
+impl GameClock {
+    /// Game seconds since the start of a new game.
+    pub fn elapsed_secs(&self) -> f64 {
+        self.ticks as f64 * TICK_SECONDS
+    }
+    /// Game seconds that passed this frame, 0 while paused.
+    pub fn delta_secs(&self) -> f32 {
+        (self.step as f64 * TICK_SECONDS) as f32
+    }
+    pub fn warp(&self) -> u32 {
+        WARP_STEPS[self.warp]
+    }
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+    pub fn warp_up(&mut self) {
+        self.warp = (self.warp + 1).min(WARP_STEPS.len() - 1);
+    }
+    pub fn warp_down(&mut self) {
+        self.warp = self.warp.saturating_sub(1);
+    }
+    /// Moves game time on by `real_seconds` at the current warp, in whole ticks.
+    pub fn advance(&mut self, real_seconds: f64) {
+        self.step = 0;
+        if self.paused {
+            return;
+        }
+        self.carry += real_seconds.min(MAX_FRAME_SECONDS) * self.warp() as f64;
+        self.step = (self.carry / TICK_SECONDS).floor() as u64;
+        self.carry -= self.step as f64 * TICK_SECONDS;
+        self.ticks += self.step;
+    }
+    /// Jumps to `ticks`, as when a game is loaded, back at 1x.
+    pub fn restore(&mut self, ticks: u64) {
+        *self = GameClock {
+            ticks,
+            paused: self.paused,
+            ..default()
+        };
+    }
+}
+
+fn control_clock(mut clock: ResMut<GameClock>, actions: Res<ActionState<GameActions>>) {
+    use GameActions::*;
+    if actions.just_pressed(PauseTime) {
+        clock.toggle_pause();
+    }
+    if actions.just_pressed(WarpUp) {
+        clock.warp_up();
+    }
+    if actions.just_pressed(WarpDown) {
+        clock.warp_down();
+    }
+}
+
+fn advance_clock(mut clock: ResMut<GameClock>, time: Res<Time<Real>>) {
+    clock.advance(time.delta_secs_f64());
+}
@@ -1,31 +1,47 @@
-mod background_stars;
+pub mod background_stars;
+pub mod condition;
+pub mod dialogue_actions;
+pub mod dialogue_graph;
+pub mod dialogue_lint;
+pub mod dialogue_runner;
+pub mod dialogue_template;
+pub mod galaxy;
+pub mod game_clock;
+pub mod localization;
 mod navigation_system;
-mod solar_system;
-mod space_position;
+pub mod solar_system;
+pub mod space_position;
 #[macro_use]
-mod input_actions;
-mod communication_system;
+pub mod input_actions;
+pub mod communication_system;
 mod notification_system;
-mod player_ship;
-mod story_system;
+pub mod player_ship;
+pub mod save_system;
+pub mod story_system;
+pub mod system_generator;
 
 use crate::input_actions::ActionState;
 use crate::story_system::{
-    ActiveDialogue, Dialogue, GameFlags, GameState, StoryPlugin, perform_action, perform_actions,
+    ActiveDialogue, Dialogue, DialogueHandle, GameFlags, GameState, StoryPlugin,
 };
 use background_stars::BackgroundStarsPlugin;
-use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin};
+use bevy::diagnostic::{
+    DiagnosticsStore, EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin,
+};
 use bevy::prelude::*;
 use bevy::sprite::Anchor;
 use bevy::window::{PresentMode, WindowResolution};
 use communication_system::*;
+use galaxy::GalaxyPlugin;
+use game_clock::{GameClock, GameClockPlugin};
 use input_actions::GameActionsPlugin;
+use localization::LocalizationPlugin;
 use navigation_system::*;
 use player_ship::*;
+use save_system::SaveSystemPlugin;
 use solar_system::*;
 use space_position::*;
 use std::collections::HashMap;
-use std::fs;
 
 pub fn run() {
     App::new().add_plugins(StarExplorer).run();
@@ -33,6 +49,7 @@ pub fn run() {
 
 fn fps_update(
     diagnostics: Res<DiagnosticsStore>,
+    clock: Res<GameClock>,
     mut query: Single<&mut Text2d, With<StatusText>>,
 ) {
     if let Some(fps) = diagnostics
@@ -43,6 +60,17 @@ fn fps_update(
     } else {
         query.0 = " N/A".to_string();
     }
+    if let Some(entities) = diagnostics
+        .get(&EntityCountDiagnosticsPlugin::ENTITY_COUNT)
+        .and_then(|count| count.value())
+    {
+        query.0.push_str(&format!("\n{entities:.0} entities"));
+    }
+    if clock.is_paused() {
+        query.0.push_str("\npaused");
+    } else {
+        query.0.push_str(&format!("\n{}x", clock.warp()));
+    }
 }
 
 struct StarExplorer;
@@ -58,6 +86,7 @@ impl Plugin for StarExplorer {
             ..Default::default()
         }));
         app.add_plugins(FrameTimeDiagnosticsPlugin::default());
+        app.add_plugins(EntityCountDiagnosticsPlugin);
         app.add_plugins(BackgroundStarsPlugin::new(200));
         app.add_plugins(NavigationSystemPlugin);
         app.add_plugins(CommunicationsSystemPlugin);
@@ -66,6 +95,10 @@ impl Plugin for StarExplorer {
         app.add_plugins(SolarSystemPlugin);
         app.add_plugins(PlayerShipPlugin);
         app.add_plugins(StoryPlugin);
+        app.add_plugins(LocalizationPlugin);
+        app.add_plugins(SaveSystemPlugin);
+        app.add_plugins(GameClockPlugin);
+        app.add_plugins(GalaxyPlugin);
         app.add_systems(Startup, startup);
         app.add_systems(Update, (fps_update, handle_input));
     }
@@ -78,8 +111,6 @@ fn startup(
     mut commands: Commands,
     mut clear: ResMut<ClearColor>,
     asset_server: ResMut<AssetServer>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
     mut action_state: ResMut<ActionState<GameActions>>,
 ) {
     use GameActions::*;
@@ -91,13 +122,35 @@ fn startup(
         ThrustReverse, ArrowDown, KeyS;
         ToggleNavMarkers, F1;
         ToggleCommsWindow, F2;
+        NextLocale, F3;
         Hail, KeyC;
+        QuickSave, F5;
+        SaveToSlot, F6;
+        NextSaveSlot, F7;
+        QuickLoad, F9;
+        LoadFromSlot, F10;
         Brake, Space;
+        SkipText, Enter;
+        ScrollCommsUp, PageUp;
+        ScrollCommsDown, PageDown;
+        PauseTime, KeyP;
+        WarpDown, Comma;
+        WarpUp, Period;
+        ToggleGalaxyMap, KeyM;
+        NextJumpTarget, Tab;
+        Jump, KeyJ;
+        ChoiceUp, ArrowUp, KeyW;
+        ChoiceDown, ArrowDown, KeyS;
         Exit, Escape;
-        Choose1, Digit1;
-        Choose2, Digit2;
-        Choose3, Digit3;
-        Choose4, Digit4;
+        Choose(0), Digit1, Numpad1;
+        Choose(1), Digit2, Numpad2;
+        Choose(2), Digit3, Numpad3;
+        Choose(3), Digit4, Numpad4;
+        Choose(4), Digit5, Numpad5;
+        Choose(5), Digit6, Numpad6;
+        Choose(6), Digit7, Numpad7;
+        Choose(7), Digit8, Numpad8;
+        Choose(8), Digit9, Numpad9;
     ]);
 
     commands.spawn((
@@ -120,19 +173,6 @@ fn startup(
         SpacePosition(Vec2::ZERO),
         Visibility::Visible,
     ));
-
-    let system_string = fs::read_to_string("system_file.json").unwrap();
-    let solar_system = serde_json::from_str(&system_string).unwrap();
-
-    load_solar_system(
-        &mut commands,
-        &asset_server,
-        Vec2::ZERO,
-        &solar_system,
-        &mut meshes,
-        &mut materials,
-        &mut 0.0,
-    );
 }
 
 #[derive(Eq, PartialEq, Hash, Copy, Clone)]
@@ -144,12 +184,28 @@ pub enum GameActions {
     ToggleNavMarkers,
     Hail,
     ToggleCommsWindow,
+    NextLocale,
+    QuickSave,
+    QuickLoad,
+    SaveToSlot,
+    LoadFromSlot,
+    NextSaveSlot,
     Brake,
+    SkipText,
+    ScrollCommsUp,
+    ScrollCommsDown,
+    PauseTime,
+    WarpDown,
+    WarpUp,
+    ToggleGalaxyMap,
+    NextJumpTarget,
+    /// Jumps to the system picked out on the galaxy map, while it's open.
+    Jump,
+    ChoiceUp,
+    ChoiceDown,
     Exit,
-    Choose1,
-    Choose2,
-    Choose3,
-    Choose4,
+    /// Takes the numbered choice on the comms window's current page, counting from 0.
+    Choose(usize),
 }
 
 fn handle_input(
@@ -160,7 +216,8 @@ fn handle_input(
     mut game_state: ResMut<GameState>,
     game_actions: Res<ActionState<GameActions>>,
     ship_position: Single<&SpacePosition, With<MyShip>>,
-    query_dialogues: Query<(Entity, &Dialogue, &SpacePosition)>,
+    query_dialogues: Query<(Entity, &DialogueHandle, &SpacePosition)>,
+    dialogue_assets: Res<Assets<Dialogue>>,
 ) {
     if game_actions.just_pressed(GameActions::Exit) {
         app_exit.send(AppExit::Success);
@@ -169,61 +226,28 @@ fn handle_input(
         // find closet Dialogue to ship.
         let mut dialogues = query_dialogues
             .iter()
-            .map(|(e, dialogue, pos)| (e, dialogue, pos.0.distance(ship_position.0)))
+            .filter_map(|(e, DialogueHandle(handle), pos)| {
+                let dialogue = dialogue_assets.get(handle)?;
+                Some((e, handle, dialogue, pos.0.distance(ship_position.0)))
+            })
             .collect::<Vec<_>>();
-        dialogues.sort_by(|(_, _, a), (_, _, b)| a.partial_cmp(b).unwrap());
-
-        if !dialogues.is_empty() {
-            let new_dialogue = dialogues.first().unwrap();
-            let new_entity = new_dialogue.0;
-            let new_dialogue = new_dialogue.1;
+        dialogues.sort_by(|(_, _, _, a), (_, _, _, b)| a.partial_cmp(b).unwrap());
 
+        // hailing always ends the current conversation first.
+        active_dialogue.end(&mut flags, &mut commands);
+        if let Some((new_entity, handle, new_dialogue, _)) = dialogues.first() {
             // set nearest dialogue to active.
-            active_dialogue.set_active(new_dialogue, new_entity);
-        } else {
-            active_dialogue.clear();
+            active_dialogue.set_active(
+                handle,
+                new_dialogue,
+                *new_entity,
+                &mut flags,
+                &mut commands,
+            );
         }
     }
     if game_actions.just_pressed(GameActions::ToggleCommsWindow) {
-        active_dialogue.clear();
-    }
-    if let Some(choices) = active_dialogue.get_choices(&flags) {
-        if game_actions.just_pressed(GameActions::Choose1) {
-            if choices.len() > 0 {
-                let choice = choices.get(0).unwrap();
-                active_dialogue.set_node_id(&choice.next);
-                if let Some(action) = choice.actions.as_ref() {
-                    perform_actions(action, &mut flags);
-                }
-            }
-        }
-        if game_actions.just_pressed(GameActions::Choose2) {
-            if choices.len() > 1 {
-                let choice = choices.get(1).unwrap();
-                active_dialogue.set_node_id(&choice.next);
-                if let Some(action) = choice.actions.as_ref() {
-                    perform_actions(action, &mut flags);
-                }
-            }
-        }
-        if game_actions.just_pressed(GameActions::Choose3) {
-            if choices.len() > 2 {
-                let choice = choices.get(2).unwrap();
-                active_dialogue.set_node_id(&choice.next);
-                if let Some(action) = choice.actions.as_ref() {
-                    perform_actions(action, &mut flags);
-                }
-            }
-        }
-        if game_actions.just_pressed(GameActions::Choose4) {
-            if choices.len() > 3 {
-                let choice = choices.get(3).unwrap();
-                active_dialogue.set_node_id(&choice.next);
-                if let Some(action) = choice.actions.as_ref() {
-                    perform_actions(action, &mut flags);
-                }
-            }
-        }
+        active_dialogue.end(&mut flags, &mut commands);
     }
 }
 
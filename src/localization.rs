@@ -0,0 +1,246 @@
+use crate::GameActions;
+use crate::input_actions::ActionState;
+use crate::story_system::Dialogue;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoadFailedEvent, AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+
+pub struct LocalizationPlugin;
+impl Plugin for LocalizationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<StringTable>();
+        app.init_asset_loader::<StringTableLoader>();
+        // Keys show as written until the tables load.
+        app.init_resource::<Localization>();
+        app.init_resource::<StringTables>();
+        app.add_systems(Startup, load_string_tables);
+        app.add_systems(Update, (update_string_tables, next_locale).chain());
+    }
+}
+
+pub const LOCALE_DIR: &str = "assets/locale";
+
+/// The locales the game loads from `locale/<locale>.strings.json` under `assets/`.
+pub const LOCALES: &[&str] = &["en", "fr"];
+
+const TABLE_EXTENSION: &str = "strings.json";
+
+/// The locale every key must exist in, used when the selected locale lacks a key.
+pub const FALLBACK_LOCALE: &str = "en";
+
+/// Dialogue strings starting with this are keys into the string tables rather than
+/// text. Start a literal string with two of them to show one.
+pub const KEY_PREFIX: char = '@';
+
+/// The string tables in `assets/locale/<locale>.strings.json`, each a flat map from
+/// key to text, and the locale dialogue is currently shown in.
+#[derive(Resource, Debug, Clone)]
+pub struct Localization {
+    locale: String,
+    tables: HashMap<String, HashMap<String, String>>,
+}
+
+impl Default for Localization {
+    fn default() -> Self {
+        Self {
+            locale: FALLBACK_LOCALE.to_string(),
+            tables: HashMap::new(),
+        }
+    }
+}
+
+impl Localization {
+    /// Loads every `.strings.json` table in `dir`, named after its file.
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let entries = fs::read_dir(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+        let mut strings = Self::default();
+        for path in entries.filter_map(|e| e.ok().map(|e| e.path())) {
+            let Some(locale) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix(TABLE_EXTENSION))
+                .and_then(|name| name.strip_suffix('.'))
+            else {
+                continue;
+            };
+            let json = fs::read_to_string(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+            let table =
+                serde_json::from_str(&json).map_err(|e| format!("{}: {e}", path.display()))?;
+            strings.insert(locale, table);
+        }
+        Ok(strings)
+    }
+
+    /// Adds or replaces the table for `locale`.
+    pub fn insert(&mut self, locale: &str, table: HashMap<String, String>) {
+        self.tables.insert(locale.to_string(), table);
+    }
+
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    /// The loaded locales, sorted.
+    pub fn locales(&self) -> Vec<&str> {
+        let mut locales = self.tables.keys().map(|l| l.as_str()).collect::<Vec<_>>();
+        locales.sort();
+        locales
+    }
+
+    /// Switches to `locale`, returning false if no table was loaded for it.
+    pub fn set_locale(&mut self, locale: &str) -> bool {
+        if self.tables.contains_key(locale) {
+            self.locale = locale.to_string();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn contains(&self, locale: &str, key: &str) -> bool {
+        self.lookup(locale, key).is_some()
+    }
+
+    /// The text for `key` in `locale` only, without falling back to English.
+    pub fn lookup(&self, locale: &str, key: &str) -> Option<&str> {
+        self.tables.get(locale)?.get(key).map(|s| s.as_str())
+    }
+
+    /// Resolves a dialogue string: keys are looked up in the current locale, then in
+    /// English, and shown as-is if neither has them. Other strings are plain text.
+    pub fn get<'a>(&'a self, text: &'a str) -> &'a str {
+        let Some(key) = string_key(text) else {
+            return text.strip_prefix(KEY_PREFIX).unwrap_or(text);
+        };
+        [self.locale.as_str(), FALLBACK_LOCALE]
+            .iter()
+            .find_map(|locale| self.tables.get(*locale)?.get(key))
+            .map_or(text, |s| s.as_str())
+    }
+}
+
+/// The key a dialogue string refers to, if it's a key rather than text.
+pub fn string_key(text: &str) -> Option<&str> {
+    let key = text.strip_prefix(KEY_PREFIX)?;
+    if key.starts_with(KEY_PREFIX) {
+        None
+    } else {
+        Some(key)
+    }
+}
+
+/// Every string key a dialogue uses, with the node it's used in.
+pub fn dialogue_keys(dialogue: &Dialogue) -> Vec<(&str, &str)> {
+    let mut keys = vec![];
+    for node in dialogue.nodes.iter() {
+        for key in node.strings().filter_map(string_key) {
+            keys.push((node.id.as_str(), key));
+        }
+    }
+    keys
+}
+
+/// One locale's string table, loaded as an asset so edits show up while playing.
+#[derive(Asset, TypePath, Debug, Clone, Deserialize)]
+pub struct StringTable(pub HashMap<String, String>);
+
+#[derive(Default)]
+pub struct StringTableLoader;
+
+#[derive(Debug)]
+pub enum StringTableLoadError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+}
+
+impl Display for StringTableLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StringTableLoadError::Io(e) => write!(f, "couldn't read string table: {e}"),
+            StringTableLoadError::Json(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for StringTableLoadError {}
+
+impl AssetLoader for StringTableLoader {
+    type Asset = StringTable;
+    type Settings = ();
+    type Error = StringTableLoadError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<StringTable, StringTableLoadError> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(StringTableLoadError::Io)?;
+        serde_json::from_slice(&bytes).map_err(StringTableLoadError::Json)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[TABLE_EXTENSION]
+    }
+}
+
+/// The string table loading for each locale.
+#[derive(Resource, Default)]
+pub struct StringTables(pub HashMap<AssetId<StringTable>, (String, Handle<StringTable>)>);
+
+fn load_string_tables(mut loading: ResMut<StringTables>, asset_server: Res<AssetServer>) {
+    for locale in LOCALES {
+        let handle: Handle<StringTable> =
+            asset_server.load(format!("locale/{locale}.{TABLE_EXTENSION}"));
+        loading.0.insert(handle.id(), (locale.to_string(), handle));
+    }
+}
+
+/// Copies tables into [`Localization`] as they load or change.
+fn update_string_tables(
+    mut events: EventReader<AssetEvent<StringTable>>,
+    mut failures: EventReader<AssetLoadFailedEvent<StringTable>>,
+    loading: Res<StringTables>,
+    tables: Res<Assets<StringTable>>,
+    mut strings: ResMut<Localization>,
+) {
+    for failure in failures.read() {
+        warn!(
+            "Couldn't load strings from {}: {}",
+            failure.path, failure.error
+        );
+    }
+    for event in events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = event
+        else {
+            continue;
+        };
+        if let (Some((locale, _)), Some(table)) = (loading.0.get(id), tables.get(*id)) {
+            strings.insert(locale, table.0.clone());
+        }
+    }
+}
+
+fn next_locale(mut strings: ResMut<Localization>, actions: Res<ActionState<GameActions>>) {
+    if !actions.just_pressed(GameActions::NextLocale) {
+        return;
+    }
+    let locales = strings.locales();
+    let next = locales
+        .iter()
+        .position(|l| *l == strings.locale())
+        .map_or(0, |i| (i + 1) % locales.len());
+    if let Some(locale) = locales.get(next).map(|l| l.to_string()) {
+        strings.set_locale(&locale);
+        info!("Switched to locale {}", locale);
+    }
+}
@@ -1,7 +1,9 @@
 use crate::GameActions;
 use crate::background_stars::BackgroundStarConfig;
+use crate::game_clock::GameClock;
 use crate::input_actions::ActionState;
 use crate::space_position::SpacePosition;
+use crate::story_system::ActiveDialogue;
 use bevy::math::{Quat, Vec2};
 use bevy::prelude::*;
 
@@ -19,13 +21,21 @@ pub struct MyShip;
 fn move_ship(
     mut transform_query: Query<(&mut Transform, &mut SpacePosition), With<MyShip>>,
     mut config: ResMut<BackgroundStarConfig>,
-    time: Res<Time<Virtual>>,
+    clock: Res<GameClock>,
+    time: Res<Time<Real>>,
     actions: Res<ActionState<GameActions>>,
+    active_dialogue: Res<ActiveDialogue>,
 ) {
     use GameActions::*;
-    let delta = time.delta_secs();
-    let turn_amount = delta * 1.5f32;
-    let speed_amount = delta * 0.5f32;
+    // The controls answer in real time, so they don't race away under warp, but the
+    // ship travels in game time like everything else.
+    let input_delta = if clock.is_paused() {
+        0.0
+    } else {
+        time.delta_secs()
+    };
+    let turn_amount = input_delta * 1.5f32;
+    let speed_amount = input_delta * 0.5f32;
 
     if actions.pressed(TurnLeft) {
         config.direction += turn_amount;
@@ -33,10 +43,12 @@ fn move_ship(
     if actions.pressed(TurnRight) {
         config.direction -= turn_amount;
     }
-    if actions.pressed(ThrustForward) {
+    // Up and down pick a choice while talking, so the throttle stays where it is.
+    let talking = active_dialogue.dialogue.is_some();
+    if actions.pressed(ThrustForward) && !talking {
         config.speed += speed_amount;
     }
-    if actions.pressed(ThrustReverse) {
+    if actions.pressed(ThrustReverse) && !talking {
         config.speed -= speed_amount;
     }
     if actions.pressed(Brake) {
@@ -52,6 +64,6 @@ fn move_ship(
 
     if let Ok((mut transform, mut space_pos)) = transform_query.get_single_mut() {
         transform.rotation = Quat::from_rotation_z(config.direction + std::f32::consts::FRAC_PI_2);
-        space_pos.0 += space_movement * 200.0 * delta;
+        space_pos.0 += space_movement * 200.0 * clock.delta_secs();
     }
 }
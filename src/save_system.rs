@@ -0,0 +1,499 @@
+use crate::GameActions;
+use crate::background_stars::BackgroundStarConfig;
+use crate::galaxy::{GalaxyMap, JumpTo};
+use crate::game_clock::GameClock;
+use crate::input_actions::ActionState;
+use crate::player_ship::MyShip;
+use crate::solar_system::SolarBody;
+use crate::space_position::SpacePosition;
+use crate::story_system::{
+    ActiveDialogue, DEFAULT_PLAYER_NAME, Dialogue, DialogueHandle, GameFlags,
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub struct SaveSystemPlugin;
+impl Plugin for SaveSystemPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SaveSlots>();
+        app.add_event::<SaveGame>();
+        app.add_event::<LoadGame>();
+        app.add_systems(Update, (handle_save_input, save_game, load_game).chain());
+    }
+}
+
+/// The save format written by this build. Bump it whenever `SaveData` changes shape,
+/// and add a migration from the previous version to `MIGRATIONS`.
+pub const SAVE_VERSION: u32 = 4;
+
+/// Upgrades a save in place from one version to the next.
+pub type Migration = fn(&mut serde_json::Value) -> Result<(), String>;
+
+/// `MIGRATIONS[i]` upgrades a version `i + 1` save to version `i + 2`, so a save of
+/// any older version can be brought up to `SAVE_VERSION` by running the tail of the
+/// chain in order.
+pub const MIGRATIONS: &[Migration] = &[add_player_name, add_clock, add_galaxy];
+
+// Every version but the first needs a migration to reach it.
+const _: () = assert!(MIGRATIONS.len() + 1 == SAVE_VERSION as usize);
+
+/// Version 2 gives every game a `player_name` variable for dialogue templates.
+fn add_player_name(save: &mut serde_json::Value) -> Result<(), String> {
+    let variables = save
+        .pointer_mut("/flags/variables")
+        .and_then(|v| v.as_object_mut())
+        .ok_or("save has no flags.variables")?;
+    variables
+        .entry("player_name")
+        .or_insert(DEFAULT_PLAYER_NAME.into());
+    Ok(())
+}
+
+/// Version 3 saves game time, which older games start again from 0.
+fn add_clock(save: &mut serde_json::Value) -> Result<(), String> {
+    let save = save.as_object_mut().ok_or("save isn't an object")?;
+    save.entry("clock")
+        .or_insert(serde_json::json!({ "ticks": 0 }));
+    Ok(())
+}
+
+/// Version 4 records which star system the ship is in. Older saves were all made in
+/// the one system there was, and load into whichever system the ship is in now.
+fn add_galaxy(save: &mut serde_json::Value) -> Result<(), String> {
+    let save = save.as_object_mut().ok_or("save isn't an object")?;
+    save.entry("system").or_insert(serde_json::Value::Null);
+    save.entry("other_systems").or_insert(serde_json::json!({}));
+    Ok(())
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SaveSlot {
+    Quick,
+    Numbered(u32),
+}
+
+/// Where save files are written, one file per slot, and which numbered slot the
+/// save and load actions use.
+#[derive(Resource)]
+pub struct SaveSlots {
+    pub dir: PathBuf,
+    pub count: u32,
+    pub selected: u32,
+}
+impl Default for SaveSlots {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("saves"),
+            count: 3,
+            selected: 1,
+        }
+    }
+}
+impl SaveSlots {
+    pub fn selected(&self) -> SaveSlot {
+        SaveSlot::Numbered(self.selected)
+    }
+    /// Moves to the next numbered slot, wrapping back to slot 1.
+    pub fn select_next(&mut self) -> SaveSlot {
+        self.selected = self.selected % self.count + 1;
+        self.selected()
+    }
+    pub fn path(&self, slot: SaveSlot) -> PathBuf {
+        match slot {
+            SaveSlot::Quick => self.dir.join("quick.json"),
+            SaveSlot::Numbered(n) => self.dir.join(format!("slot{n}.json")),
+        }
+    }
+}
+
+/// Writes the current game to a slot at the end of the frame.
+#[derive(Event, Debug, Clone)]
+pub struct SaveGame(pub SaveSlot);
+
+/// Replaces the current game with the one saved in a slot.
+#[derive(Event, Debug, Clone)]
+pub struct LoadGame(pub SaveSlot);
+
+#[derive(Serialize, Deserialize)]
+pub struct SaveData {
+    pub version: u32,
+    pub flags: GameFlags,
+    /// Dialogue progress by `SolarBody` name, since entity ids change between runs.
+    pub dialogues: HashMap<String, DialogueProgress>,
+    pub ship: ShipState,
+    pub clock: GameClock,
+    /// The id of the star system the ship is in, which `dialogues` belongs to.
+    pub system: Option<String>,
+    /// Dialogue progress in the systems the ship has left, by system id.
+    pub other_systems: HashMap<String, HashMap<String, DialogueProgress>>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct DialogueProgress {
+    #[serde(default)]
+    pub node: Option<String>,
+    #[serde(default)]
+    pub resume: Option<String>,
+    #[serde(default)]
+    pub visited: HashSet<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ShipState {
+    pub position: [f32; 2],
+    pub speed: f32,
+    pub direction: f32,
+}
+
+/// A node a save refers to that isn't in the body's dialogue any more.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissingNode {
+    pub body: String,
+    pub node: String,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    /// The save has no usable `version` field.
+    MissingVersion,
+    /// The save was written by a newer build than this one.
+    TooNew(u32),
+    /// Upgrading the save from `version` failed.
+    Migration {
+        version: u32,
+        message: String,
+    },
+}
+
+impl Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Io(e) => write!(f, "{e}"),
+            SaveError::Json(e) => write!(f, "{e}"),
+            SaveError::MissingVersion => write!(f, "save has no version"),
+            SaveError::TooNew(version) => write!(
+                f,
+                "save version {version} is newer than this build, which reads up to {SAVE_VERSION}"
+            ),
+            SaveError::Migration { version, message } => {
+                write!(f, "couldn't upgrade save from version {version}: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl SaveData {
+    pub fn capture(
+        flags: &GameFlags,
+        active_dialogue: &ActiveDialogue,
+        bodies: &HashMap<Entity, String>,
+        position: &SpacePosition,
+        config: &BackgroundStarConfig,
+        clock: &GameClock,
+    ) -> Self {
+        SaveData {
+            version: SAVE_VERSION,
+            flags: flags.clone(),
+            dialogues: capture_progress(active_dialogue, bodies),
+            ship: ShipState {
+                position: position.0.to_array(),
+                speed: config.speed,
+                direction: config.direction,
+            },
+            clock: clock.clone(),
+            system: None,
+            other_systems: HashMap::new(),
+        }
+    }
+
+    /// Closes any open conversation, without running its actions, and restores
+    /// everything in the save. Progress for bodies that no longer exist is dropped.
+    pub fn apply(
+        self,
+        flags: &mut GameFlags,
+        active_dialogue: &mut ActiveDialogue,
+        bodies: &HashMap<String, Entity>,
+        (position, config): (&mut SpacePosition, &mut BackgroundStarConfig),
+        clock: &mut GameClock,
+        commands: &mut Commands,
+    ) {
+        *flags = self.flags;
+        active_dialogue.close(commands);
+        active_dialogue.node_id.clear();
+        active_dialogue.resume.clear();
+        active_dialogue.visited.clear();
+        apply_progress(self.dialogues, active_dialogue, bodies);
+        position.0 = Vec2::from_array(self.ship.position);
+        config.speed = self.ship.speed;
+        config.direction = self.ship.direction;
+        clock.restore(self.clock.ticks);
+    }
+
+    /// Reads a save of any version up to `SAVE_VERSION`, upgrading older ones.
+    pub fn read(path: &Path) -> Result<Self, SaveError> {
+        let json = fs::read_to_string(path).map_err(SaveError::Io)?;
+        let mut value = serde_json::from_str(&json).map_err(SaveError::Json)?;
+        migrate(&mut value, MIGRATIONS)?;
+        serde_json::from_value(value).map_err(SaveError::Json)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), SaveError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(SaveError::Io)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(SaveError::Json)?;
+        fs::write(path, json).map_err(SaveError::Io)
+    }
+}
+
+/// Dialogue progress with each of `bodies`, by name, leaving out bodies that haven't
+/// been talked to.
+pub fn capture_progress(
+    active_dialogue: &ActiveDialogue,
+    bodies: &HashMap<Entity, String>,
+) -> HashMap<String, DialogueProgress> {
+    let mut dialogues = HashMap::new();
+    for (entity, name) in bodies.iter() {
+        let progress = DialogueProgress {
+            node: active_dialogue.node_id.get(entity).cloned(),
+            resume: active_dialogue.resume.get(entity).cloned(),
+            visited: active_dialogue
+                .visited
+                .get(entity)
+                .cloned()
+                .unwrap_or_default(),
+        };
+        if progress.node.is_some() || progress.resume.is_some() || !progress.visited.is_empty() {
+            dialogues.insert(name.clone(), progress);
+        }
+    }
+    dialogues
+}
+
+/// Restores progress captured by `capture_progress` to the bodies with those names.
+/// Progress for bodies that don't exist is dropped.
+pub fn apply_progress(
+    progress: HashMap<String, DialogueProgress>,
+    active_dialogue: &mut ActiveDialogue,
+    bodies: &HashMap<String, Entity>,
+) {
+    for (name, progress) in progress {
+        let Some(&entity) = bodies.get(&name) else {
+            continue;
+        };
+        if let Some(node) = progress.node {
+            active_dialogue.node_id.insert(entity, node);
+        }
+        if let Some(resume) = progress.resume {
+            active_dialogue.resume.insert(entity, resume);
+        }
+        active_dialogue.visited.insert(entity, progress.visited);
+    }
+}
+
+/// The nodes `progress` refers to that aren't in the dialogue of the body they
+/// belong to, sorted. Bodies without a loaded dialogue aren't checked.
+pub fn missing_nodes(
+    progress: &HashMap<String, DialogueProgress>,
+    dialogues: &HashMap<String, &Dialogue>,
+) -> Vec<MissingNode> {
+    let mut missing = vec![];
+    for (body, progress) in progress.iter() {
+        let Some(dialogue) = dialogues.get(body) else {
+            continue;
+        };
+        let nodes = progress
+            .node
+            .iter()
+            .chain(progress.resume.iter())
+            .chain(progress.visited.iter());
+        for node in nodes {
+            if dialogue.node(node).is_none() {
+                missing.push(MissingNode {
+                    body: body.clone(),
+                    node: node.clone(),
+                });
+            }
+        }
+    }
+    missing.sort_by(|a, b| (&a.body, &a.node).cmp(&(&b.body, &b.node)));
+    missing.dedup();
+    missing
+}
+
+/// Takes the nodes `missing_nodes` finds out of `progress` with a warning for each,
+/// returning them.
+pub fn forget_missing_nodes(
+    progress: &mut HashMap<String, DialogueProgress>,
+    dialogues: &HashMap<String, &Dialogue>,
+) -> Vec<MissingNode> {
+    let missing = missing_nodes(progress, dialogues);
+    for MissingNode { body, node } in missing.iter() {
+        warn!("Dropped progress in `{node}` ({body}): its dialogue no longer has that node");
+        let Some(progress) = progress.get_mut(body) else {
+            continue;
+        };
+        if progress.node.as_ref() == Some(node) {
+            progress.node = None;
+        }
+        if progress.resume.as_ref() == Some(node) {
+            progress.resume = None;
+        }
+        progress.visited.remove(node);
+    }
+    missing
+}
+
+/// Runs the migrations a save needs to reach the version after the last one in
+/// `migrations`, updating its `version` as each one succeeds.
+pub fn migrate(save: &mut serde_json::Value, migrations: &[Migration]) -> Result<(), SaveError> {
+    let latest = migrations.len() as u64 + 1;
+    let mut version = save
+        .get("version")
+        .and_then(|v| v.as_u64())
+        .filter(|v| *v > 0)
+        .ok_or(SaveError::MissingVersion)?;
+    if version > latest {
+        return Err(SaveError::TooNew(version as u32));
+    }
+    while version < latest {
+        let migration = migrations[version as usize - 1];
+        migration(save).map_err(|message| SaveError::Migration {
+            version: version as u32,
+            message,
+        })?;
+        version += 1;
+        save["version"] = version.into();
+    }
+    Ok(())
+}
+
+fn handle_save_input(
+    mut commands: Commands,
+    mut slots: ResMut<SaveSlots>,
+    actions: Res<ActionState<GameActions>>,
+) {
+    use GameActions::*;
+    if actions.just_pressed(QuickSave) {
+        commands.send_event(SaveGame(SaveSlot::Quick));
+    }
+    if actions.just_pressed(QuickLoad) {
+        commands.send_event(LoadGame(SaveSlot::Quick));
+    }
+    if actions.just_pressed(SaveToSlot) {
+        commands.send_event(SaveGame(slots.selected()));
+    }
+    if actions.just_pressed(LoadFromSlot) {
+        commands.send_event(LoadGame(slots.selected()));
+    }
+    if actions.just_pressed(NextSaveSlot) {
+        let slot = slots.select_next();
+        info!("Selected save {slot:?}");
+    }
+}
+
+fn save_game(
+    mut events: EventReader<SaveGame>,
+    slots: Res<SaveSlots>,
+    (flags, active_dialogue, clock, galaxy): (
+        Res<GameFlags>,
+        Res<ActiveDialogue>,
+        Res<GameClock>,
+        Res<GalaxyMap>,
+    ),
+    bodies: Query<(Entity, &SolarBody)>,
+    ship: Single<&SpacePosition, With<MyShip>>,
+    config: Res<BackgroundStarConfig>,
+) {
+    for SaveGame(slot) in events.read() {
+        let bodies = bodies
+            .iter()
+            .map(|(entity, body)| (entity, body.name.clone()))
+            .collect();
+        let mut data = SaveData::capture(&flags, &active_dialogue, &bodies, &ship, &config, &clock);
+        data.system = Some(galaxy.current.clone());
+        data.other_systems = galaxy.progress.clone();
+        let path = slots.path(*slot);
+        match data.write(&path) {
+            Ok(()) => info!("Saved game to {}", path.display()),
+            Err(e) => warn!("Couldn't save game to {}: {}", path.display(), e),
+        }
+    }
+}
+
+fn load_game(
+    mut commands: Commands,
+    mut events: EventReader<LoadGame>,
+    slots: Res<SaveSlots>,
+    (mut flags, mut active_dialogue, mut clock, mut galaxy): (
+        ResMut<GameFlags>,
+        ResMut<ActiveDialogue>,
+        ResMut<GameClock>,
+        ResMut<GalaxyMap>,
+    ),
+    bodies: Query<(Entity, &SolarBody, Option<&DialogueHandle>)>,
+    dialogue_assets: Res<Assets<Dialogue>>,
+    (mut ship, mut config): (
+        Single<&mut SpacePosition, With<MyShip>>,
+        ResMut<BackgroundStarConfig>,
+    ),
+) {
+    for LoadGame(slot) in events.read() {
+        let path = slots.path(*slot);
+        let dialogues = bodies
+            .iter()
+            .filter_map(|(_, body, handle)| {
+                let dialogue = dialogue_assets.get(&handle?.0)?;
+                Some((body.name.clone(), dialogue))
+            })
+            .collect();
+        let mut data = match SaveData::read(&path) {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("Couldn't load game from {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        // Progress in other systems is checked against their bodies when the ship gets
+        // there, by `restore_progress`.
+        if data
+            .system
+            .as_ref()
+            .is_none_or(|system| *system == galaxy.current)
+        {
+            forget_missing_nodes(&mut data.dialogues, &dialogues);
+        }
+        let bodies = bodies
+            .iter()
+            .map(|(entity, body, _)| (body.name.clone(), entity))
+            .collect();
+        // A save made in another system jumps there, and its progress goes back to
+        // that system's bodies once they spawn.
+        galaxy.progress = std::mem::take(&mut data.other_systems);
+        if let Some(system) = data.system.clone().filter(|s| *s != galaxy.current) {
+            let progress = std::mem::take(&mut data.dialogues);
+            galaxy.progress.insert(system.clone(), progress);
+            commands.send_event(JumpTo {
+                system,
+                arrival: Some(Vec2::from_array(data.ship.position)),
+            });
+        }
+        data.apply(
+            &mut flags,
+            &mut active_dialogue,
+            &bodies,
+            (&mut ship, &mut config),
+            &mut clock,
+            &mut commands,
+        );
+        info!("Loaded game from {}", path.display());
+    }
+}
@@ -1,23 +1,37 @@
+use crate::game_clock::GameClock;
 use crate::space_position::SpacePosition;
-use crate::story_system::Dialogue;
+use crate::story_system::DialogueHandle;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy::render::view::NoFrustumCulling;
 use bevy::sprite::Anchor;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::f32::consts::TAU;
-use std::fs;
+use std::collections::HashMap;
+use std::f32::consts::{PI, TAU};
+use std::fmt::Display;
 use std::path::PathBuf;
 
 pub struct SolarSystemPlugin;
 
 impl Plugin for SolarSystemPlugin {
     fn build(&self, app: &mut App) {
+        app.init_asset::<SolarBodyDescriptor>();
+        app.init_asset_loader::<SolarSystemLoader>();
+        app.init_resource::<SolarSystem>();
+        app.add_systems(Update, spawn_solar_system);
         app.add_systems(PreUpdate, update_orbitals);
     }
 }
 
-#[derive(Serialize, Deserialize)]
+/// The system the game starts in when there's no galaxy to start from.
+pub const DEFAULT_SYSTEM: &str = "systems/sol.system.json";
+
+/// A body and everything orbiting it, as written in `assets/systems/*.system.json`.
+#[derive(Asset, TypePath, Serialize, Deserialize, Debug, Clone)]
+#[serde(try_from = "serde_json::Value")]
 pub struct SolarBodyDescriptor {
     pub name: String,
     pub size: f32,
@@ -27,17 +41,254 @@ pub struct SolarBodyDescriptor {
     pub tint: Option<Color>,
     #[serde(default)]
     pub image: Option<PathBuf>,
+    /// What the body says when hailed, such as `dialogue/earth.json`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dialogue: Option<PathBuf>,
     pub children: Vec<SolarBodyDescriptor>,
 }
+// Bodies are deserialized one at a time so errors can say where in the tree they are.
+#[derive(Deserialize)]
+struct BodyDef {
+    name: String,
+    size: f32,
+    #[serde(default)]
+    orbit: Option<OrbitalBody>,
+    #[serde(default)]
+    tint: Option<Color>,
+    #[serde(default)]
+    image: Option<PathBuf>,
+    #[serde(default)]
+    dialogue: Option<PathBuf>,
+    #[serde(default)]
+    children: Vec<serde_json::Value>,
+}
+
+impl TryFrom<serde_json::Value> for SolarBodyDescriptor {
+    type Error = BodyError;
+    fn try_from(value: serde_json::Value) -> Result<Self, Self::Error> {
+        let root = Self::from_value(value, "$".to_string())?;
+        root.check_names("$".to_string(), &mut HashMap::new())?;
+        Ok(root)
+    }
+}
+
+impl SolarBodyDescriptor {
+    fn from_value(value: serde_json::Value, path: String) -> Result<Self, BodyError> {
+        let name = value.get("name").and_then(|n| n.as_str()).map(String::from);
+        let error = |message: String| BodyError {
+            path: path.clone(),
+            name: name.clone(),
+            message,
+        };
+        let def: BodyDef = serde_json::from_value(value).map_err(|e| error(e.to_string()))?;
+        if let Some(orbit) = def.orbit
+            && !(0.0..1.0).contains(&orbit.eccentricity)
+        {
+            return Err(error(format!(
+                "eccentricity must be at least 0 and below 1, not {}",
+                orbit.eccentricity
+            )));
+        }
+        let children = def
+            .children
+            .into_iter()
+            .enumerate()
+            .map(|(index, child)| Self::from_value(child, format!("{path}.children[{index}]")))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            name: def.name,
+            size: def.size,
+            orbit: def.orbit,
+            tint: def.tint,
+            image: def.image,
+            dialogue: def.dialogue,
+            children,
+        })
+    }
+
+    /// Bodies are told apart by name when the system is reloaded, so no two may share
+    /// one. `seen` has where each name was first used.
+    fn check_names(
+        &self,
+        path: String,
+        seen: &mut HashMap<String, String>,
+    ) -> Result<(), BodyError> {
+        if let Some(first) = seen.get(&self.name) {
+            return Err(BodyError {
+                message: format!("the name is already used by the body at {first}"),
+                name: Some(self.name.clone()),
+                path,
+            });
+        }
+        seen.insert(self.name.clone(), path.clone());
+        for (index, child) in self.children.iter().enumerate() {
+            child.check_names(format!("{path}.children[{index}]"), seen)?;
+        }
+        Ok(())
+    }
+}
+
+/// A body in a system file that couldn't be read, with where it is in the file,
+/// such as `$.children[2]`.
+#[derive(Debug)]
+pub struct BodyError {
+    pub path: String,
+    pub name: Option<String>,
+    pub message: String,
+}
+
+impl Display for BodyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "body `{}` at {}: {}", name, self.path, self.message),
+            None => write!(f, "body at {}: {}", self.path, self.message),
+        }
+    }
+}
+
+impl std::error::Error for BodyError {}
+
+/// Loads `assets/systems/*.system.json`. The longer extension keeps them apart from
+/// dialogue files, which are plain `.json`.
+#[derive(Default)]
+pub struct SolarSystemLoader;
+
+#[derive(Debug)]
+pub enum SolarSystemLoadError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+}
+
+impl Display for SolarSystemLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolarSystemLoadError::Io(e) => write!(f, "couldn't read system: {e}"),
+            SolarSystemLoadError::Json(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for SolarSystemLoadError {}
+
+impl AssetLoader for SolarSystemLoader {
+    type Asset = SolarBodyDescriptor;
+    type Settings = ();
+    type Error = SolarSystemLoadError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<SolarBodyDescriptor, SolarSystemLoadError> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(SolarSystemLoadError::Io)?;
+        serde_json::from_slice(&bytes).map_err(SolarSystemLoadError::Json)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["system.json"]
+    }
+}
+
+/// The system being flown in. Its bodies are spawned when it loads, and updated in
+/// place when the file changes. The galaxy map sets the handle to jump to another one.
+#[derive(Resource, Default)]
+pub struct SolarSystem {
+    pub handle: Handle<SolarBodyDescriptor>,
+    /// The system whose bodies are out there now, if any.
+    pub spawned: Option<AssetId<SolarBodyDescriptor>>,
+}
+
+/// Spawns the system's bodies once it has loaded, and again whenever it's modified.
+/// Bodies are matched by name so a reload keeps their entities, and with them any
+/// conversation in progress; bodies no longer in the file are despawned.
+pub fn spawn_solar_system(
+    mut events: EventReader<AssetEvent<SolarBodyDescriptor>>,
+    systems: Res<Assets<SolarBodyDescriptor>>,
+    mut solar_system: ResMut<SolarSystem>,
+    bodies: Query<(Entity, &SolarBody)>,
+    mut spawner: BodySpawner,
+) {
+    let id = solar_system.handle.id();
+    // Generated systems are added rather than loaded, so there's no load event to
+    // wait for; a system is spawned as soon as it's there.
+    let modified = events.read().any(|event| event.is_modified(id));
+    if solar_system.spawned == Some(id) && !modified {
+        return;
+    }
+    let Some(descriptor) = systems.get(id) else {
+        return;
+    };
+    let mut existing = bodies
+        .iter()
+        .map(|(entity, body)| (body.name.clone(), entity))
+        .collect();
+    spawner.spawn(Vec2::ZERO, descriptor, &mut 0.0, &mut existing);
+    for entity in existing.into_values() {
+        // Recursively, which also takes it out of its parent's `Children`.
+        spawner.commands.entity(entity).despawn_recursive();
+    }
+    solar_system.spawned = Some(id);
+}
 
-#[derive(Component, Copy, Clone, Serialize, Deserialize)]
+#[derive(Component, Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct OrbitalBody {
     pub distance: f32, // Distance from parents SpacePosition
     #[serde(default)]
     pub period: Option<f32>, // Time in seconds to complete a revolution
-    pub start: f32,    // Starting position in radians
+    #[serde(alias = "mean_anomaly")]
+    pub start: f32, // Mean anomaly at time 0 in radians, the starting angle for circles
+    /// 0 for a circle, up to but not including 1 for ever longer ellipses.
+    #[serde(default)]
+    pub eccentricity: f32,
+    /// The angle of the closest approach to the parent, in radians.
+    #[serde(default)]
+    pub periapsis: f32,
+    /// Orbits clockwise instead of counterclockwise.
+    #[serde(default)]
+    pub retrograde: bool,
+}
+
+impl OrbitalBody {
+    /// Where the body is relative to its parent after `time` seconds, on an ellipse
+    /// whose semi-major axis is `semi_major`.
+    pub fn position(&self, semi_major: f32, time: f64) -> Vec2 {
+        let speed = self.period.map_or(1.0, |period| TAU as f64 / period as f64);
+        let direction = if self.retrograde { -1.0 } else { 1.0 };
+        // Wrapped in f64 so orbits stay smooth however long the game has run.
+        let turned = (direction * speed * time).rem_euclid(TAU as f64) as f32;
+        let mean_anomaly = self.start + turned;
+        let e = self.eccentricity;
+        let eccentric_anomaly = solve_kepler(mean_anomaly, e);
+        let offset = Vec2::new(
+            semi_major * (eccentric_anomaly.cos() - e),
+            semi_major * (1.0 - e * e).sqrt() * eccentric_anomaly.sin(),
+        );
+        Vec2::from_angle(self.periapsis).rotate(offset)
+    }
 }
 
+/// Solves Kepler's equation `M = E - e sin E` for the eccentric anomaly `E`.
+pub fn solve_kepler(mean_anomaly: f32, eccentricity: f32) -> f32 {
+    let mean_anomaly = mean_anomaly.rem_euclid(TAU);
+    // Starting from pi converges for any eccentricity below 1.
+    let mut anomaly = if eccentricity > 0.8 { PI } else { mean_anomaly };
+    for _ in 0..KEPLER_ITERATIONS {
+        let error = anomaly - eccentricity * anomaly.sin() - mean_anomaly;
+        if error.abs() < 1e-6 {
+            break;
+        }
+        anomaly -= error / (1.0 - eccentricity * anomaly.cos());
+    }
+    anomaly
+}
+
+const KEPLER_ITERATIONS: usize = 20;
+
 #[derive(Component)]
 pub struct SolarBody {
     pub name: String,
@@ -59,112 +310,123 @@ impl SolarBody {
 #[derive(Component)]
 pub struct BodySize(pub f32);
 
-pub fn load_solar_system(
-    commands: &mut Commands,
-    asset_server: &ResMut<AssetServer>,
-    position: Vec2,
-    config: &SolarBodyDescriptor,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<ColorMaterial>>,
-    layer: &mut f32,
-) -> Entity {
-    let space_position = SpacePosition(position);
-    let entity = commands
-        .spawn((
+/// Spawns bodies from a [`SolarBodyDescriptor`].
+#[derive(SystemParam)]
+pub struct BodySpawner<'w, 's> {
+    pub commands: Commands<'w, 's>,
+    asset_server: Res<'w, AssetServer>,
+    meshes: ResMut<'w, Assets<Mesh>>,
+    materials: ResMut<'w, Assets<ColorMaterial>>,
+    current_materials: Query<'w, 's, &'static MeshMaterial2d<ColorMaterial>>,
+}
+
+impl BodySpawner<'_, '_> {
+    /// Spawns `config` and its children, reusing the entity in `existing` with the
+    /// same name and taking it out of the map.
+    pub fn spawn(
+        &mut self,
+        position: Vec2,
+        config: &SolarBodyDescriptor,
+        layer: &mut f32,
+        existing: &mut HashMap<String, Entity>,
+    ) -> Entity {
+        let body = (
             SolarBody::named(&config.name).of_radius(config.size),
-            space_position.clone(),
+            SpacePosition(position),
             //text,
             Anchor::Custom(Vec2::new(0.0, 1.0)),
             Transform::from_xyz(0.0, 0.0, *layer),
             Visibility::Visible,
             BodySize(config.size),
             NoFrustumCulling,
-        ))
-        .id();
-    let path = format!("assets/dialogue/{}.json", &config.name.to_lowercase());
-    if fs::metadata(&path).is_ok() {
-        let dialogue: Dialogue = serde_json::from_str(
-            fs::read_to_string(path)
-                .expect("Couldn't read a file.")
-                .as_str(),
-        )
-        .expect("Couldn't form the Dialogue");
-        //println!("Loaded dialogue for {}\n {:?}", &config.name, &dialogue);
-        commands.entity(entity).insert(dialogue);
-    }
-    match (config.tint, config.image.clone()) {
-        (Some(tint), Some(pathbuf)) => {
-            let image = asset_server.load(pathbuf);
-            let mut sprite = Sprite::from_image(image);
-            sprite.color = tint;
-            sprite.custom_size = Some(Vec2::splat(config.size));
-            commands.entity(entity).insert(sprite);
-        }
-        (None, Some(pathbuf)) => {
-            let image = asset_server.load(pathbuf);
-            let mut sprite = Sprite::from_image(image);
-            sprite.custom_size = Some(Vec2::splat(config.size));
-            commands.entity(entity).insert(sprite);
+        );
+        let reused = existing.remove(&config.name);
+        let entity = match reused {
+            Some(entity) => {
+                self.commands
+                    .entity(entity)
+                    .remove::<(
+                        Sprite,
+                        Mesh2d,
+                        MeshMaterial2d<ColorMaterial>,
+                        OrbitalBody,
+                        DialogueHandle,
+                    )>()
+                    .insert(body);
+                entity
+            }
+            None => self.commands.spawn(body).id(),
+        };
+        if let Some(path) = config.dialogue.clone() {
+            self.commands
+                .entity(entity)
+                .insert(DialogueHandle(self.asset_server.load(path)));
         }
-        (Some(tint), None) => {
-            commands.entity(entity).insert((
-                Mesh2d(meshes.add(Circle::new(config.size))),
-                MeshMaterial2d(materials.add(ColorMaterial::from_color(tint))),
-            ));
+        match (config.tint, config.image.clone()) {
+            (Some(tint), Some(pathbuf)) => {
+                let image = self.asset_server.load(pathbuf);
+                let mut sprite = Sprite::from_image(image);
+                sprite.color = tint;
+                sprite.custom_size = Some(Vec2::splat(config.size));
+                self.commands.entity(entity).insert(sprite);
+            }
+            (None, Some(pathbuf)) => {
+                let image = self.asset_server.load(pathbuf);
+                let mut sprite = Sprite::from_image(image);
+                sprite.custom_size = Some(Vec2::splat(config.size));
+                self.commands.entity(entity).insert(sprite);
+            }
+            (Some(tint), None) => {
+                self.commands.entity(entity).insert((
+                    Mesh2d(self.meshes.add(Circle::new(config.size))),
+                    MeshMaterial2d(self.materials.add(ColorMaterial::from_color(tint))),
+                ));
+            }
+            (None, None) => {
+                // A body without a colour gets a random one, which it keeps on reload.
+                let material = reused
+                    .and_then(|entity| self.current_materials.get(entity).ok())
+                    .map(|material| material.0.clone())
+                    .unwrap_or_else(|| {
+                        let mut rng = rand::thread_rng();
+                        self.materials
+                            .add(ColorMaterial::from_color(rand_color(&mut rng)))
+                    });
+                self.commands.entity(entity).insert((
+                    Mesh2d(self.meshes.add(Circle::new(config.size))),
+                    MeshMaterial2d(material),
+                ));
+            }
+        };
+
+        if let Some(orbit) = &config.orbit {
+            self.commands.entity(entity).insert(*orbit);
         }
-        (None, None) => {
-            let mut rng = rand::thread_rng();
-            commands.entity(entity).insert((
-                Mesh2d(meshes.add(Circle::new(config.size))),
-                MeshMaterial2d(materials.add(ColorMaterial::from_color(rand_color(&mut rng)))),
-            ));
+        let mut children: Vec<Entity> = vec![];
+        *layer += 1.0;
+        for child in &config.children {
+            children.push(self.spawn(position, child, layer, existing));
         }
-    };
-
-    if let Some(orbit) = &config.orbit {
-        commands.entity(entity).insert(orbit.clone());
-    }
-    let mut children: Vec<Entity> = vec![];
-    *layer += 1.0;
-    for child in &config.children {
-        children.push(load_solar_system(
-            commands,
-            asset_server,
-            position,
-            child,
-            meshes,
-            materials,
-            layer,
-        ));
+        self.commands
+            .entity(entity)
+            .add_children(children.as_slice());
+        entity
     }
-    commands.entity(entity).add_children(children.as_slice());
-    entity
 }
 
 pub fn update_orbitals(
     parents: Query<((&SpacePosition, &BodySize), Option<&Children>)>,
     children: Query<(&OrbitalBody, &BodySize)>,
-    time: Res<Time>,
+    clock: Res<GameClock>,
     mut commands: Commands,
 ) {
     for ((SpacePosition(pos), BodySize(size)), maybe_children) in parents.iter() {
         if let Some(children_entities) = maybe_children {
             for &child_entity in children_entities.iter() {
                 if let Ok((orbital_body, BodySize(child_size))) = children.get(child_entity) {
-                    let speed = if let Some(period) = orbital_body.period {
-                        TAU / period
-                    } else {
-                        1.0
-                    };
-                    let elapsed_time = (time.elapsed_secs() * speed) + orbital_body.start;
-                    let new_position = Vec2::new(
-                        pos.x
-                            + elapsed_time.cos()
-                                * (orbital_body.distance + (size + child_size) * 2.0),
-                        pos.y
-                            + elapsed_time.sin()
-                                * (orbital_body.distance + (size + child_size) * 2.0),
-                    );
+                    let semi_major = orbital_body.distance + (size + child_size) * 2.0;
+                    let new_position =
+                        *pos + orbital_body.position(semi_major, clock.elapsed_secs());
                     commands
                         .entity(child_entity)
                         .insert(SpacePosition(new_position));
@@ -1,8 +1,14 @@
+use crate::condition::{Condition, Value, check};
+use crate::dialogue_actions::{Action, ActionRegistry, perform_actions};
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
 use bevy::prelude::*;
 use bevy::sprite::Anchor;
-use serde::Deserialize;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
-use std::fmt::Debug;
+use std::fmt::{Debug, Display};
 
 pub struct StoryPlugin;
 impl Plugin for StoryPlugin {
@@ -10,8 +16,19 @@ impl Plugin for StoryPlugin {
         app.init_resource::<GameState>();
         app.init_resource::<GameFlags>();
         app.init_resource::<ActiveDialogue>();
+        app.init_resource::<ActionRegistry>();
+        app.init_asset::<Dialogue>();
+        app.init_asset_loader::<DialogueLoader>();
+        app.add_event::<DialogueStarted>();
+        app.add_event::<NodeEntered>();
+        app.add_event::<ChoiceSelected>();
+        app.add_event::<FlagChanged>();
+        app.add_event::<DialogueEnded>();
         app.add_systems(Startup, setup);
-        app.add_systems(Update, handle_story);
+        app.add_systems(
+            Update,
+            (reload_active_dialogue, handle_story, send_flag_changes).chain(),
+        );
     }
 }
 
@@ -28,20 +45,13 @@ fn setup(mut commands: Commands, asset_server: ResMut<AssetServer>) {
     ));
 }
 
-fn handle_story(
-    mut flags: ResMut<GameFlags>,
-    active_dialogue: ResMut<ActiveDialogue>,
-    mut text: Single<&mut Text2d, With<StoryDebug>>,
-) {
+fn handle_story(flags: Res<GameFlags>, mut text: Single<&mut Text2d, With<StoryDebug>>) {
     text.0.clear();
-    if let Some(dialogue) = &active_dialogue.dialogue {
-        dialogue.apply_on_enter(&active_dialogue.node_id(), &mut flags);
-        // let message = active_dialogue.get_message(&flags);
-        // if let Some(msg) = message {
-        //     text.0.push_str(msg);
-        //     text.0.push_str("\n");
-        // }
-    }
+    // let message = active_dialogue.get_message(&flags);
+    // if let Some(msg) = message {
+    //     text.0.push_str(msg);
+    //     text.0.push_str("\n");
+    // }
     // if let Some(choices) = &active_dialogue.get_choices(&flags) {
     //     text.0.push_str("--------\n");
     //     for choice in choices.iter() {
@@ -50,87 +60,373 @@ fn handle_story(
     //     }
     // }
     text.0
-        .push_str(format!("\n\n---flags---\n{:?}", flags.0).as_str());
+        .push_str(format!("\n\n---flags---\n{:?}", *flags).as_str());
+}
+
+fn send_flag_changes(mut flags: ResMut<GameFlags>, mut flag_changed: EventWriter<FlagChanged>) {
+    if flags.changes.is_empty() {
+        return;
+    }
+    flag_changed.send_batch(flags.take_changes());
+}
+
+/// Keeps the comms window showing the latest version of a dialogue edited on disk.
+fn reload_active_dialogue(
+    mut events: EventReader<AssetEvent<Dialogue>>,
+    dialogues: Res<Assets<Dialogue>>,
+    mut active_dialogue: ResMut<ActiveDialogue>,
+) {
+    for event in events.read() {
+        if let AssetEvent::Modified { id } = event {
+            if active_dialogue.handle.as_ref().map(|h| h.id()) == Some(*id) {
+                if let Some(dialogue) = dialogues.get(*id) {
+                    active_dialogue.dialogue = Some(dialogue.clone_self());
+                }
+            }
+        }
+    }
+}
+
+/// The `next` target that ends a conversation and closes the comms window.
+pub const END_NODE: &str = "end";
+
+/// Sent when the player hails a body and its dialogue opens at `node`.
+#[derive(Event, Debug, Clone)]
+pub struct DialogueStarted {
+    pub speaker: Entity,
+    pub node: String,
+}
+
+/// Sent whenever the conversation moves to a node, including the first one.
+#[derive(Event, Debug, Clone)]
+pub struct NodeEntered {
+    pub speaker: Entity,
+    pub node: String,
+}
+
+/// Sent when the player takes the visible choice at `index` in `node`.
+#[derive(Event, Debug, Clone)]
+pub struct ChoiceSelected {
+    pub speaker: Entity,
+    pub node: String,
+    pub index: usize,
+    pub next: String,
 }
 
-#[derive(Deserialize, Debug, Component, Clone)]
+/// Sent when a flag or variable actually changes. Cleared flags and unset variables
+/// have the value `false`, which is what conditions read for them. `speaker` is who
+/// was being talked to when the change was made.
+#[derive(Event, Debug, Clone)]
+pub struct FlagChanged {
+    pub speaker: Option<Entity>,
+    pub name: String,
+    pub value: Value,
+}
+
+/// Sent when a conversation ends, either through an `end` choice or by closing the
+/// comms window.
+#[derive(Event, Debug, Clone)]
+pub struct DialogueEnded {
+    pub speaker: Entity,
+    pub node: String,
+}
+
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+#[serde(try_from = "DialogueDef")]
 pub struct Dialogue {
     pub entry: String,
     pub nodes: Vec<Node>,
+    /// Who says texts that don't name a speaker.
+    pub speaker: Option<Speaker>,
+    /// Other speakers, named by a text's `speaker`.
+    pub speakers: HashMap<String, Speaker>,
+}
+// Nodes are deserialized one at a time so errors can name the node they came from.
+#[derive(Deserialize)]
+struct DialogueDef {
+    entry: String,
+    nodes: Vec<serde_json::Value>,
+    #[serde(default)]
+    speaker: Option<Speaker>,
+    #[serde(default)]
+    speakers: HashMap<String, Speaker>,
+}
+/// How someone on the other end of the comms is shown. Anything left out uses the
+/// window's defaults, and the name falls back to the body being talked to.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Speaker {
+    /// Shown above the message and by `{speaker}`. Can be a string key.
+    pub name: Option<String>,
+    /// An image path under `assets/`.
+    pub portrait: Option<String>,
+    /// The colour of the name and the panel's tint, as `#rrggbb`.
+    pub accent: Option<String>,
+    /// A font path under `assets/` for the message, such as
+    /// `fonts/DroidSerif-Regular.ttf`.
+    pub font: Option<String>,
+}
+impl Speaker {
+    /// The accent colour, or `None` if it isn't set or isn't valid hex.
+    pub fn accent_color(&self) -> Option<Color> {
+        let accent = self.accent.as_deref()?;
+        Srgba::hex(accent).ok().map(Color::from)
+    }
 }
 #[derive(Deserialize, Debug, Clone)]
 pub struct Node {
     pub id: String,
     pub texts: Vec<Text>,
     pub choices: Vec<Choice>,
-    pub on_enter: Option<Vec<String>>,
+    /// Run each time the conversation moves into this node.
+    pub on_enter: Option<Vec<Action>>,
+    /// Run each time the conversation leaves this node, after the chosen choice's
+    /// actions, or when the conversation is closed while here.
+    #[serde(default)]
+    pub on_exit: Option<Vec<Action>>,
+    /// Only apply `on_enter` and `on_exit` on the first visit to this node.
+    #[serde(default)]
+    pub once: bool,
+    /// How to choose between texts whose conditions pass.
+    #[serde(default)]
+    pub pick: TextPick,
+}
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TextPick {
+    /// The first one, in the order they're written.
+    #[default]
+    First,
+    /// A random one by `weight`, picked on entering the node and never the same one
+    /// twice in a row when there's another to show.
+    Random,
+}
+impl Node {
+    /// Every action in the node: `on_enter`, `on_exit`, then each choice's.
+    pub fn actions(&self) -> impl Iterator<Item = &Action> {
+        let choice_actions = self.choices.iter().flat_map(|c| c.actions.iter().flatten());
+        self.on_enter
+            .iter()
+            .flatten()
+            .chain(self.on_exit.iter().flatten())
+            .chain(choice_actions)
+    }
+    /// Every string shown to the player: texts, choices, then choice hints.
+    pub fn strings(&self) -> impl Iterator<Item = &str> {
+        let texts = self.texts.iter().map(|t| t.text.as_str());
+        let choices = self.choices.iter().map(|c| c.text.as_str());
+        let hints = self.choices.iter().filter_map(|c| c.hint.as_deref());
+        texts.chain(choices).chain(hints)
+    }
 }
 #[derive(Deserialize, Debug, Clone)]
 pub struct Text {
-    pub condition: Option<String>,
+    pub condition: Option<Condition>,
     pub text: String,
+    /// Which of the dialogue's `speakers` says this, instead of its `speaker`.
+    #[serde(default)]
+    pub speaker: Option<String>,
+    /// How likely this text is relative to the others in a `random` node.
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+fn default_weight() -> u32 {
+    1
 }
 #[derive(Deserialize, Debug, Clone)]
 pub struct Choice {
     pub text: String,
     pub next: String,
-    pub condition: Option<String>,
-    pub actions: Option<Vec<String>>,
+    pub condition: Option<Condition>,
+    pub actions: Option<Vec<Action>>,
+    /// When set, the choice is shown greyed out with this hint while its condition
+    /// fails, instead of being hidden.
+    #[serde(default)]
+    pub hint: Option<String>,
 }
 #[derive(Debug, Resource)]
 pub struct GameState {
     pub hail: bool,
 }
+/// The dialogue a solar body answers hails with.
+#[derive(Component)]
+pub struct DialogueHandle(pub Handle<Dialogue>);
+
 #[derive(Resource)]
 pub struct ActiveDialogue {
     pub dialogue: Option<Dialogue>,
+    pub handle: Option<Handle<Dialogue>>,
     pub choices: Option<Vec<Choice>>,
     pub entity: Option<Entity>,
+    /// The node each entity's conversation is at. Outside a conversation only a save
+    /// made during one leaves a node here, and the next hail picks up from it.
     pub node_id: HashMap<Entity, String>,
+    /// Where the next hail of each entity starts instead of the dialogue's `entry`,
+    /// set by the `resume:<node>` action.
+    pub resume: HashMap<Entity, String>,
+    /// Nodes each entity's conversation has left, so `once` nodes don't apply their
+    /// actions again.
+    pub visited: HashMap<Entity, HashSet<String>>,
+    /// The text picked on entering the current node.
+    pub text_index: Option<usize>,
+    /// The last text picked for each node of each entity, so random nodes don't show
+    /// it again straight away.
+    pub last_text: HashMap<(Entity, String), usize>,
+    pub rng: StdRng,
 }
 impl ActiveDialogue {
-    pub fn set_active(&mut self, dialogue: &Dialogue, entity: Entity) {
+    pub fn set_active(
+        &mut self,
+        handle: &Handle<Dialogue>,
+        dialogue: &Dialogue,
+        entity: Entity,
+        flags: &mut GameFlags,
+        commands: &mut Commands,
+    ) {
+        let node_id = self
+            .node_id
+            .get(&entity)
+            .or_else(|| self.resume.get(&entity))
+            .cloned()
+            .unwrap_or_else(|| dialogue.entry.clone());
         self.dialogue = Some(dialogue.clone_self());
+        self.handle = Some(handle.clone());
         self.entity = Some(entity);
-        self.node_id.insert(entity, "start".to_string());
+        commands.send_event(DialogueStarted {
+            speaker: entity,
+            node: node_id.clone(),
+        });
+        self.enter(&node_id, flags, commands);
     }
     pub fn clear(&mut self) {
-        if self.entity.is_some() {
-            self.node_id
-                .insert(self.entity.unwrap(), "start".to_string());
+        if let Some(entity) = self.entity {
+            self.node_id.remove(&entity);
         }
         self.dialogue = None;
+        self.handle = None;
         self.entity = None;
         self.choices = None;
+        self.text_index = None;
     }
-    pub fn node_id(&self) -> String {
-        if self.entity.is_none() {
-            "start".to_string()
+    /// Closes the conversation, if one is open, running the current node's `on_exit`.
+    pub fn end(&mut self, flags: &mut GameFlags, commands: &mut Commands) {
+        self.exit(flags, commands);
+        self.close(commands);
+    }
+    /// Closes the conversation, if one is open, without running any actions, as when
+    /// loading a game replaces the one it was part of.
+    pub fn close(&mut self, commands: &mut Commands) {
+        if let Some(speaker) = self.entity {
+            commands.send_event(DialogueEnded {
+                speaker,
+                node: self.node_id(),
+            });
+        }
+        self.clear();
+    }
+    /// Takes the visible choice at `index`: runs its actions, leaves the current node
+    /// and moves to its `next` node, ending the conversation if that's [`END_NODE`]. Returns false if there
+    /// is no such choice.
+    pub fn choose(&mut self, index: usize, flags: &mut GameFlags, commands: &mut Commands) -> bool {
+        let (Some(speaker), Some(choices)) = (self.entity, self.get_choices(flags)) else {
+            return false;
+        };
+        let Some(choice) = choices.get(index) else {
+            return false;
+        };
+        commands.send_event(ChoiceSelected {
+            speaker,
+            node: self.node_id(),
+            index,
+            next: choice.next.clone(),
+        });
+        if let Some(actions) = choice.actions.as_ref() {
+            perform_actions(actions, flags, commands, self.entity);
+        }
+        if choice.next == END_NODE {
+            self.end(flags, commands);
         } else {
-            self.node_id
-                .get(&self.entity.unwrap())
-                .unwrap_or(&"start".to_string())
-                .clone()
+            self.exit(flags, commands);
+            self.enter(&choice.next, flags, commands);
         }
+        true
+    }
+    // The only places nodes are entered and left, so their actions run once per visit.
+    fn enter(&mut self, node_id: &str, flags: &mut GameFlags, commands: &mut Commands) {
+        let Some(speaker) = self.entity else {
+            return;
+        };
+        self.set_node_id(node_id);
+        commands.send_event(NodeEntered {
+            speaker,
+            node: node_id.to_string(),
+        });
+        if let Some(actions) = self.node_actions(|n| n.on_enter.as_ref()) {
+            perform_actions(&actions, flags, commands, self.entity);
+        }
+        self.pick_text(flags);
+    }
+    fn pick_text(&mut self, flags: &GameFlags) {
+        let (Some(entity), Some(dialogue)) = (self.entity, self.dialogue.as_ref()) else {
+            return;
+        };
+        let key = (entity, self.node_id());
+        let previous = self.last_text.get(&key).copied();
+        self.text_index = dialogue.pick_text(&key.1, flags, previous, &mut self.rng);
+        if let Some(index) = self.text_index {
+            self.last_text.insert(key, index);
+        }
+    }
+    fn exit(&mut self, flags: &mut GameFlags, commands: &mut Commands) {
+        let Some(speaker) = self.entity else {
+            return;
+        };
+        if let Some(actions) = self.node_actions(|n| n.on_exit.as_ref()) {
+            perform_actions(&actions, flags, commands, self.entity);
+        }
+        let node_id = self.node_id();
+        self.visited.entry(speaker).or_default().insert(node_id);
+    }
+    /// The current node's actions picked by `list`, unless it's a `once` node that
+    /// has already been visited.
+    fn node_actions(&self, list: impl Fn(&Node) -> Option<&Vec<Action>>) -> Option<Vec<Action>> {
+        let node_id = self.node_id();
+        let node = self.dialogue.as_ref()?.node(&node_id)?;
+        let visited = self
+            .entity
+            .and_then(|e| self.visited.get(&e))
+            .is_some_and(|v| v.contains(&node_id));
+        if node.once && visited {
+            return None;
+        }
+        list(node).cloned()
+    }
+    pub fn node_id(&self) -> String {
+        let entry = self.dialogue.as_ref().map(|d| d.entry.clone());
+        self.entity
+            .and_then(|entity| self.node_id.get(&entity).cloned())
+            .or(entry)
+            .unwrap_or_default()
     }
     pub fn set_node_id(&mut self, node_id: &str) {
         self.node_id
             .insert(self.entity.unwrap(), node_id.to_string());
     }
+    /// The text picked when the node was entered, or the first one that passes if the
+    /// flags have changed since and it no longer does.
     pub fn get_message(&self, flags: &GameFlags) -> Option<&str> {
-        if self.dialogue.is_none() {
-            None
-        } else {
-            Some(
-                &self
-                    .dialogue
-                    .as_ref()
-                    .unwrap()
-                    .get_text(&self.node_id(), flags)
-                    .unwrap()
-                    .text,
-            )
-        }
+        self.current_text(flags).map(|t| t.text.as_str())
+    }
+    /// Who says the current text.
+    pub fn get_speaker(&self, flags: &GameFlags) -> Option<&Speaker> {
+        let text = self.current_text(flags)?;
+        self.dialogue.as_ref()?.speaker_of(text)
+    }
+    fn current_text(&self, flags: &GameFlags) -> Option<&Text> {
+        let dialogue = self.dialogue.as_ref()?;
+        let node_id = self.node_id();
+        self.text_index
+            .and_then(|index| dialogue.text_at(&node_id, index, flags))
+            .or_else(|| dialogue.get_text(&node_id, flags))
     }
     pub fn get_choices(&self, flags: &GameFlags) -> Option<Vec<Choice>> {
         if self.dialogue.is_none() {
@@ -142,15 +438,28 @@ impl ActiveDialogue {
                 .get_choices(&self.node_id(), flags)
         }
     }
+    /// The choices to list in the comms window, see `Dialogue::choice_list`.
+    pub fn choice_list(&self, flags: &GameFlags) -> Vec<(Choice, bool)> {
+        self.dialogue
+            .as_ref()
+            .map(|d| d.choice_list(&self.node_id(), flags))
+            .unwrap_or_default()
+    }
 }
 
 impl FromWorld for ActiveDialogue {
     fn from_world(_world: &mut World) -> Self {
         ActiveDialogue {
             dialogue: None,
+            handle: None,
             choices: None,
             entity: None,
             node_id: HashMap::new(),
+            resume: HashMap::new(),
+            visited: HashMap::new(),
+            text_index: None,
+            last_text: HashMap::new(),
+            rng: StdRng::from_entropy(),
         }
     }
 }
@@ -161,41 +470,246 @@ impl FromWorld for GameState {
     }
 }
 
+impl TryFrom<DialogueDef> for Dialogue {
+    type Error = DialogueError;
+    fn try_from(def: DialogueDef) -> Result<Self, Self::Error> {
+        let nodes = def
+            .nodes
+            .into_iter()
+            .enumerate()
+            .map(|(index, value)| {
+                let id = value
+                    .get("id")
+                    .and_then(|id| id.as_str())
+                    .map_or_else(|| format!("#{index}"), |id| id.to_string());
+                if id == END_NODE {
+                    return Err(DialogueError {
+                        node: id,
+                        message: format!("`{END_NODE}` is reserved for ending the conversation"),
+                    });
+                }
+                serde_json::from_value(value).map_err(|error| DialogueError {
+                    node: id,
+                    message: error.to_string(),
+                })
+            })
+            .collect::<Result<Vec<Node>, _>>()?;
+        for node in nodes.iter() {
+            let unknown = node
+                .texts
+                .iter()
+                .filter_map(|t| t.speaker.as_ref())
+                .find(|speaker| !def.speakers.contains_key(*speaker));
+            if let Some(speaker) = unknown {
+                return Err(DialogueError {
+                    node: node.id.clone(),
+                    message: format!("unknown speaker `{speaker}`"),
+                });
+            }
+        }
+        Ok(Dialogue {
+            entry: def.entry,
+            nodes,
+            speaker: def.speaker,
+            speakers: def.speakers,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct DialogueError {
+    pub node: String,
+    pub message: String,
+}
+
+impl Display for DialogueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "node `{}`: {}", self.node, self.message)
+    }
+}
+
+impl std::error::Error for DialogueError {}
+
+/// Loads `assets/dialogue/*.json`, rejecting malformed conditions and unknown actions.
+pub struct DialogueLoader {
+    actions: ActionRegistry,
+}
+
+impl FromWorld for DialogueLoader {
+    fn from_world(world: &mut World) -> Self {
+        DialogueLoader {
+            actions: world.resource::<ActionRegistry>().clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DialogueLoadError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Invalid(DialogueError),
+}
+
+impl Display for DialogueLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DialogueLoadError::Io(e) => write!(f, "couldn't read dialogue: {e}"),
+            DialogueLoadError::Json(e) => write!(f, "{e}"),
+            DialogueLoadError::Invalid(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for DialogueLoadError {}
+
+impl AssetLoader for DialogueLoader {
+    type Asset = Dialogue;
+    type Settings = ();
+    type Error = DialogueLoadError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Dialogue, DialogueLoadError> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(DialogueLoadError::Io)?;
+        let dialogue: Dialogue = serde_json::from_slice(&bytes).map_err(DialogueLoadError::Json)?;
+        dialogue
+            .validate_actions(&self.actions)
+            .map_err(DialogueLoadError::Invalid)?;
+        Ok(dialogue)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["json"]
+    }
+}
+
 impl Dialogue {
+    pub fn node(&self, node_id: &str) -> Option<&Node> {
+        self.nodes.iter().find(|n| n.id == node_id)
+    }
+
+    /// The default speaker, then the named ones by name.
+    pub fn all_speakers(&self) -> Vec<(Option<&str>, &Speaker)> {
+        let mut named = self
+            .speakers
+            .iter()
+            .map(|(id, s)| (Some(id.as_str()), s))
+            .collect::<Vec<_>>();
+        named.sort_by_key(|(id, _)| *id);
+        self.speaker
+            .iter()
+            .map(|s| (None, s))
+            .chain(named)
+            .collect()
+    }
+
+    /// The speaker `text` names, or the dialogue's own.
+    pub fn speaker_of(&self, text: &Text) -> Option<&Speaker> {
+        match text.speaker.as_ref() {
+            Some(id) => self.speakers.get(id),
+            None => self.speaker.as_ref(),
+        }
+    }
+
     pub fn get_text(&self, node_id: &str, flags: &GameFlags) -> Option<&Text> {
-        self.nodes
+        self.node(node_id).and_then(|node| {
+            node.texts
+                .iter()
+                .find(|t| check(t.condition.as_ref(), flags))
+        })
+    }
+
+    /// The text at `index` in the node, if its condition passes.
+    pub fn text_at(&self, node_id: &str, index: usize, flags: &GameFlags) -> Option<&Text> {
+        self.node(node_id)?
+            .texts
+            .get(index)
+            .filter(|t| check(t.condition.as_ref(), flags))
+    }
+
+    /// Chooses which of the node's texts to show, following its `pick`. `previous` is
+    /// skipped when a random node has another text to show.
+    pub fn pick_text(
+        &self,
+        node_id: &str,
+        flags: &GameFlags,
+        previous: Option<usize>,
+        rng: &mut impl Rng,
+    ) -> Option<usize> {
+        let node = self.node(node_id)?;
+        let mut candidates = node
+            .texts
             .iter()
-            .find(|n| n.id == node_id)
-            .and_then(|node| {
-                node.texts
-                    .iter()
-                    .find(|t| flags.check(t.condition.as_deref()))
-            })
+            .enumerate()
+            .filter(|(_, t)| check(t.condition.as_ref(), flags))
+            .collect::<Vec<_>>();
+        if node.pick == TextPick::First || candidates.len() < 2 {
+            return candidates.first().map(|(i, _)| *i);
+        }
+        if candidates
+            .iter()
+            .filter(|(i, _)| Some(*i) != previous)
+            .any(|(_, t)| t.weight > 0)
+        {
+            candidates.retain(|(i, _)| Some(*i) != previous);
+        }
+        let total = candidates.iter().map(|(_, t)| t.weight).sum::<u32>();
+        if total == 0 {
+            return candidates.first().map(|(i, _)| *i);
+        }
+        let mut roll = rng.gen_range(0..total);
+        for (index, text) in candidates {
+            if roll < text.weight {
+                return Some(index);
+            }
+            roll -= text.weight;
+        }
+        None
     }
 
     pub fn get_choices(&self, node_id: &str, flags: &GameFlags) -> Option<Vec<Choice>> {
-        self.nodes.iter().find(|n| n.id == node_id).map(|node| {
+        self.node(node_id).map(|node| {
             node.choices
                 .iter()
-                .filter(|c| {
-                    let condition = c.condition.as_deref();
-                    let result = flags.check(condition);
-                    result
-                })
+                .filter(|c| check(c.condition.as_ref(), flags))
                 .cloned()
                 .collect()
         })
     }
 
-    // Apply on_enter actions for a node, modifying the game state
-    fn apply_on_enter(&self, node_id: &str, flags: &mut GameFlags) {
-        if let Some(node) = self.nodes.iter().find(|n| n.id == node_id) {
-            if let Some(actions) = &node.on_enter {
-                for action in actions {
-                    perform_action(action, flags);
-                }
+    /// The choices to list, each with whether it can be taken: the ones whose
+    /// conditions pass, and the failing ones that have a `hint` to show.
+    pub fn choice_list(&self, node_id: &str, flags: &GameFlags) -> Vec<(Choice, bool)> {
+        let Some(node) = self.node(node_id) else {
+            return vec![];
+        };
+        node.choices
+            .iter()
+            .map(|c| (c, check(c.condition.as_ref(), flags)))
+            .filter(|(c, enabled)| *enabled || c.hint.is_some())
+            .map(|(c, enabled)| (c.clone(), enabled))
+            .collect()
+    }
+
+    /// Checks every action in the dialogue against the registry, so unknown actions
+    /// are reported when the dialogue loads rather than when it runs.
+    pub fn validate_actions(&self, registry: &ActionRegistry) -> Result<(), DialogueError> {
+        for node in self.nodes.iter() {
+            for action in node.actions() {
+                registry.validate(action).map_err(|message| DialogueError {
+                    node: node.id.clone(),
+                    message,
+                })?;
             }
         }
+        Ok(())
     }
 
     fn clone_self(&self) -> Self {
@@ -203,162 +717,120 @@ impl Dialogue {
     }
 }
 
-#[derive(Resource)]
-pub struct GameFlags(HashSet<String>);
-
-impl Debug for GameFlags {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self.0)
-    }
+#[derive(Resource, Clone, Serialize, Deserialize)]
+pub struct GameFlags {
+    flags: HashSet<String>,
+    variables: HashMap<String, Value>,
+    // Drained into `FlagChanged` events each frame.
+    #[serde(skip)]
+    changes: Vec<FlagChanged>,
+    // Who changes are credited to while dialogue actions run.
+    #[serde(skip)]
+    speaker: Option<Entity>,
 }
 
+/// The `player_name` variable a new game starts with.
+pub const DEFAULT_PLAYER_NAME: &str = "Captain";
+
 impl Default for GameFlags {
     fn default() -> Self {
-        Self(HashSet::new())
+        Self {
+            flags: HashSet::new(),
+            variables: HashMap::from([(
+                "player_name".to_string(),
+                Value::Str(DEFAULT_PLAYER_NAME.to_string()),
+            )]),
+            changes: vec![],
+            speaker: None,
+        }
+    }
+}
+
+impl Debug for GameFlags {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.flags)?;
+        for (name, value) in self.variables.iter() {
+            write!(f, "\n{name} = {value}")?;
+        }
+        Ok(())
     }
 }
 
 impl GameFlags {
     pub fn set(&mut self, flag: &str) {
-        self.0.insert(flag.to_string());
+        if self.flags.insert(flag.to_string()) {
+            self.changed(flag, Value::Bool(true));
+        }
     }
     pub fn remove(&mut self, flag: &str) {
-        self.0.remove(flag);
-    }
-    fn check(&self, condition: Option<&str>) -> bool {
-        let condition_str = condition.unwrap_or("");
-        if condition_str.trim().is_empty() {
-            return true;
-        }
-        let tokens = tokenize_condition(condition_str);
-        if tokens.is_empty() {
-            return true;
+        if self.flags.remove(flag) {
+            self.changed(flag, Value::Bool(false));
         }
-        let mut index = 0;
-        self.parse_or(&tokens, &mut index)
     }
-
-    fn parse_or(&self, tokens: &[String], index: &mut usize) -> bool {
-        let mut result = self.parse_and(tokens, index);
-        while *index < tokens.len() && tokens[*index] == "||" {
-            *index += 1;
-            let right = self.parse_and(tokens, index);
-            result |= right;
+    pub fn is_set(&self, flag: &str) -> bool {
+        self.flags.contains(flag)
+    }
+    pub fn set_var(&mut self, name: &str, value: Value) {
+        if self.variables.get(name) != Some(&value) {
+            self.changed(name, value.clone());
+            self.variables.insert(name.to_string(), value);
         }
-        result
     }
-
-    fn parse_and(&self, tokens: &[String], index: &mut usize) -> bool {
-        let mut result = self.parse_unary(tokens, index);
-        while *index < tokens.len() && tokens[*index] == "&&" {
-            *index += 1;
-            let right = self.parse_unary(tokens, index);
-            result &= right;
+    pub fn remove_var(&mut self, name: &str) {
+        if self.variables.remove(name).is_some() {
+            self.changed(name, Value::Bool(false));
         }
+    }
+    fn changed(&mut self, name: &str, value: Value) {
+        self.changes.push(FlagChanged {
+            speaker: self.speaker,
+            name: name.to_string(),
+            value,
+        });
+    }
+    /// Runs `change`, crediting the changes it makes to `speaker`.
+    pub fn changed_by<R>(
+        &mut self,
+        speaker: Option<Entity>,
+        change: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        let previous = std::mem::replace(&mut self.speaker, speaker);
+        let result = change(self);
+        self.speaker = previous;
         result
     }
-
-    fn parse_unary(&self, tokens: &[String], index: &mut usize) -> bool {
-        if *index < tokens.len() && tokens[*index] == "!" {
-            *index += 1;
-            !self.parse_unary(tokens, index)
-        } else if *index < tokens.len() {
-            let flag = &tokens[*index];
-            *index += 1;
-            self.0.contains(flag)
-        } else {
-            false
-        }
+    /// The changes made since they were last taken, oldest first.
+    pub fn take_changes(&mut self) -> Vec<FlagChanged> {
+        std::mem::take(&mut self.changes)
     }
-}
-
-pub fn perform_actions(actions: &[String], state: &mut GameFlags) {
-    for action in actions {
-        perform_action(action, state);
+    /// Adds `amount` to a numeric variable, treating a missing variable as zero.
+    pub fn add_var(&mut self, name: &str, amount: &Value) -> Result<(), String> {
+        let current = self.variables.get(name).cloned().unwrap_or(Value::Int(0));
+        let sum = match (&current, amount) {
+            (Value::Int(a), Value::Int(b)) => match a.checked_add(*b) {
+                Some(sum) => Value::Int(sum),
+                None => return Err(format!("adding {amount} to {name} = {current} overflows")),
+            },
+            (Value::Int(a), Value::Float(b)) => Value::Float(*a as f64 + b),
+            (Value::Float(a), Value::Int(b)) => Value::Float(a + *b as f64),
+            (Value::Float(a), Value::Float(b)) => Value::Float(a + b),
+            _ => return Err(format!("can't add {amount} to {name} = {current}")),
+        };
+        self.set_var(name, sum);
+        Ok(())
     }
-}
-// Perform an action to modify the game state
-pub fn perform_action(action: &str, state: &mut GameFlags) {
-    let parts: Vec<&str> = action.split(':').collect();
-    if parts.len() == 2 {
-        match parts[0] {
-            "set_flag" => {
-                state.set(parts[1]);
-            }
-            "remove_flag" => {
-                state.remove(parts[1]);
-            }
-            _ => println!("Unknown action: {}", action),
-        }
-    } else {
-        println!("Invalid action format: {}", action);
+    pub fn var(&self, name: &str) -> Option<&Value> {
+        self.variables.get(name)
     }
-}
-
-fn tokenize_condition(condition: &str) -> Vec<String> {
-    let mut tokens = Vec::new();
-    let mut chars = condition.chars().peekable();
-
-    while let Some(&ch) = chars.peek() {
-        match ch {
-            '!' => {
-                tokens.push("!".to_string());
-                chars.next();
-            }
-            '&' => {
-                chars.next();
-                if chars.peek() == Some(&'&') {
-                    tokens.push("&&".to_string());
-                    chars.next();
-                } else {
-                    let mut flag = String::new();
-                    flag.push('&');
-                    while let Some(&next_ch) = chars.peek() {
-                        if next_ch.is_alphanumeric() || next_ch == '_' {
-                            flag.push(next_ch);
-                            chars.next();
-                        } else {
-                            break;
-                        }
-                    }
-                    tokens.push(flag);
-                }
-            }
-            '|' => {
-                chars.next();
-                if chars.peek() == Some(&'|') {
-                    tokens.push("||".to_string());
-                    chars.next();
-                } else {
-                    let mut flag = String::new();
-                    flag.push('|');
-                    while let Some(&next_ch) = chars.peek() {
-                        if next_ch.is_alphanumeric() || next_ch == '_' {
-                            flag.push(next_ch);
-                            chars.next();
-                        } else {
-                            break;
-                        }
-                    }
-                    tokens.push(flag);
-                }
-            }
-            _ if ch.is_alphanumeric() || ch == '_' => {
-                let mut flag = String::new();
-                while let Some(&next_ch) = chars.peek() {
-                    if next_ch.is_alphanumeric() || next_ch == '_' {
-                        flag.push(next_ch);
-                        chars.next();
-                    } else {
-                        break;
-                    }
-                }
-                tokens.push(flag);
-            }
-            _ => {
-                chars.next(); // Skip whitespace or invalid characters
-            }
-        }
+    /// The names of every variable currently set.
+    pub fn var_names(&self) -> impl Iterator<Item = &str> {
+        self.variables.keys().map(|k| k.as_str())
+    }
+    /// Looks up a name used in a condition: a variable if one is set, otherwise the flag.
+    pub fn value(&self, name: &str) -> Value {
+        self.variables
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| Value::Bool(self.is_set(name)))
     }
-    tokens
 }
@@ -0,0 +1,318 @@
+use crate::solar_system::{OrbitalBody, SolarBodyDescriptor};
+use bevy::prelude::*;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+
+/// A kind of star, from hot blue giants to cool red dwarfs.
+pub struct StarClass {
+    pub letter: char,
+    pub size: f32,
+    /// Relative to the Sun; heavier stars make their planets orbit faster.
+    pub mass: f32,
+    pub tint: [f32; 3],
+    /// How often this class comes up relative to the others.
+    pub weight: u32,
+    pub planets: RangeInclusive<usize>,
+}
+
+pub const STAR_CLASSES: &[StarClass] = &[
+    StarClass {
+        letter: 'B',
+        size: 520.0,
+        mass: 8.0,
+        tint: [0.65, 0.75, 1.0],
+        weight: 1,
+        planets: 1..=4,
+    },
+    StarClass {
+        letter: 'A',
+        size: 420.0,
+        mass: 2.0,
+        tint: [0.85, 0.9, 1.0],
+        weight: 3,
+        planets: 2..=6,
+    },
+    StarClass {
+        letter: 'F',
+        size: 340.0,
+        mass: 1.3,
+        tint: [1.0, 0.97, 0.88],
+        weight: 6,
+        planets: 3..=8,
+    },
+    StarClass {
+        letter: 'G',
+        size: 300.0,
+        mass: 1.0,
+        tint: [1.0, 0.92, 0.7],
+        weight: 8,
+        planets: 3..=9,
+    },
+    StarClass {
+        letter: 'K',
+        size: 240.0,
+        mass: 0.7,
+        tint: [1.0, 0.75, 0.45],
+        weight: 10,
+        planets: 2..=7,
+    },
+    StarClass {
+        letter: 'M',
+        size: 160.0,
+        mass: 0.3,
+        tint: [1.0, 0.5, 0.35],
+        weight: 14,
+        planets: 1..=5,
+    },
+];
+
+/// The image every generated star is drawn with, tinted by its class.
+const STAR_IMAGE: &str = "the_sun.png";
+
+/// The period of an orbit `KEPLER_DISTANCE` out from a star of mass 1, which sets
+/// the scale for every other period by Kepler's third law. It matches Earth in the
+/// bundled system.
+const KEPLER_PERIOD: f32 = 600.0;
+const KEPLER_DISTANCE: f32 = 3000.0;
+
+/// Where the frost line is around a star of mass 1, between Mars and Jupiter in the
+/// bundled system.
+const FROST_LINE: f32 = 6000.0;
+
+/// Makes up names from syllables, never giving the same one twice.
+#[derive(Default)]
+pub struct NameGenerator {
+    used: HashSet<String>,
+}
+
+const ONSETS: &[&str] = &[
+    "b", "d", "dr", "g", "k", "kr", "l", "m", "n", "p", "qu", "r", "s", "t", "th", "v", "z",
+];
+const VOWELS: &[&str] = &["a", "e", "i", "o", "u", "ae", "ai", "io", "ou"];
+const CODAS: &[&str] = &["", "", "", "l", "n", "r", "s", "th", "x"];
+
+impl NameGenerator {
+    pub fn next(&mut self, rng: &mut impl Rng) -> String {
+        loop {
+            let syllables = rng.gen_range(2..=3);
+            let mut name = String::new();
+            for index in 0..syllables {
+                // Names start with a vowel now and then, like Io or Europa.
+                if index > 0 || rng.gen_bool(0.8) {
+                    name.push_str(ONSETS.choose(rng).unwrap());
+                }
+                name.push_str(VOWELS.choose(rng).unwrap());
+                if index + 1 == syllables {
+                    name.push_str(CODAS.choose(rng).unwrap());
+                }
+            }
+            let name = capitalize(&name);
+            if self.used.insert(name.clone()) {
+                return name;
+            }
+        }
+    }
+}
+
+fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    chars.next().map_or(String::new(), |first| {
+        first.to_uppercase().chain(chars).collect()
+    })
+}
+
+/// Generates a star and everything orbiting it. The same seed always gives the same
+/// system.
+pub fn generate_system(seed: u64) -> SolarBodyDescriptor {
+    // `StdRng` may change algorithm between rand versions, which would change every
+    // seeded system in the galaxy.
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut names = NameGenerator::default();
+    let class = STAR_CLASSES
+        .choose_weighted(&mut rng, |c| c.weight)
+        .unwrap();
+    let star_name = names.next(&mut rng);
+
+    let planet_count = rng.gen_range(class.planets.clone());
+    // Gas giants and ice worlds form beyond the frost line, where ices can condense.
+    // Brighter stars push it out, and brightness climbs steeply with mass.
+    let frost_line = FROST_LINE * class.mass.powf(1.75);
+    let mut distance = class.size * rng.gen_range(2.5..4.0);
+    let mut children = vec![];
+    let mut belt = false;
+    for _ in 0..planet_count {
+        distance *= rng.gen_range(1.4..2.0);
+        // At most one belt, in a gap between planets, like the Sun's.
+        if !belt && children.len() >= 2 && rng.gen_bool(0.35) {
+            belt = true;
+            let belt_name = names.next(&mut rng);
+            children.extend(asteroid_belt(&mut rng, class, &belt_name, distance));
+            distance *= rng.gen_range(1.4..2.0);
+        }
+        let kind = if distance < frost_line {
+            PlanetKind::Rocky
+        } else if rng.gen_bool(0.65) {
+            PlanetKind::GasGiant
+        } else {
+            PlanetKind::Ice
+        };
+        children.push(planet(&mut rng, &mut names, class, kind, distance));
+    }
+    if rng.gen_bool(0.5) {
+        children.push(comet(&mut rng, &mut names, class, distance));
+    }
+
+    SolarBodyDescriptor {
+        name: star_name,
+        size: class.size,
+        orbit: None,
+        tint: Some(color(class.tint)),
+        image: Some(PathBuf::from(STAR_IMAGE)),
+        dialogue: None,
+        children,
+    }
+}
+
+#[derive(Copy, Clone)]
+enum PlanetKind {
+    Rocky,
+    GasGiant,
+    Ice,
+}
+
+const ROCKY_TINTS: &[[f32; 3]] = &[
+    [0.6, 0.58, 0.55],
+    [0.85, 0.4, 0.25],
+    [0.9, 0.8, 0.55],
+    [0.3, 0.55, 0.95],
+    [0.45, 0.6, 0.35],
+];
+const GAS_GIANT_TINTS: &[[f32; 3]] = &[[0.8, 0.65, 0.5], [0.9, 0.8, 0.6], [0.7, 0.5, 0.4]];
+const ICE_TINTS: &[[f32; 3]] = &[[0.6, 0.8, 0.9], [0.4, 0.55, 0.9], [0.75, 0.85, 0.95]];
+
+fn planet(
+    rng: &mut ChaCha8Rng,
+    names: &mut NameGenerator,
+    class: &StarClass,
+    kind: PlanetKind,
+    distance: f32,
+) -> SolarBodyDescriptor {
+    let (size, tints, moons): (f32, _, _) = match kind {
+        PlanetKind::Rocky => (rng.gen_range(12.0..45.0), ROCKY_TINTS, 0..=1),
+        PlanetKind::GasGiant => (rng.gen_range(80.0..150.0), GAS_GIANT_TINTS, 1..=4),
+        PlanetKind::Ice => (rng.gen_range(40.0..75.0), ICE_TINTS, 0..=3),
+    };
+    let name = names.next(rng);
+    let moon_count = rng.gen_range(moons);
+    let mut moon_distance = size * 0.5;
+    let mut children = vec![];
+    for _ in 0..moon_count {
+        moon_distance += rng.gen_range(30.0..90.0);
+        let moon_size = rng.gen_range(4.0..size.min(30.0) * 0.4);
+        let orbit = OrbitalBody {
+            distance: round(moon_distance, 0),
+            // Moons go round in minutes, so they're worth watching.
+            period: Some(round(rng.gen_range(40.0..180.0), 0)),
+            start: angle(rng),
+            eccentricity: round(rng.gen_range(0.0..0.05), 3),
+            periapsis: angle(rng),
+            // Captured moons often orbit backwards, like Triton.
+            retrograde: rng.gen_bool(0.1),
+        };
+        children.push(SolarBodyDescriptor {
+            name: names.next(rng),
+            size: round(moon_size, 1),
+            orbit: Some(orbit),
+            tint: Some(color(ROCKY_TINTS[0])),
+            image: None,
+            dialogue: None,
+            children: vec![],
+        });
+    }
+    SolarBodyDescriptor {
+        name,
+        size: round(size, 1),
+        orbit: Some(orbit(rng, class, distance, 0.0..0.08)),
+        tint: Some(color(*tints.choose(rng).unwrap())),
+        image: None,
+        dialogue: None,
+        children,
+    }
+}
+
+fn asteroid_belt(
+    rng: &mut ChaCha8Rng,
+    class: &StarClass,
+    name: &str,
+    distance: f32,
+) -> Vec<SolarBodyDescriptor> {
+    (1..=rng.gen_range(6..=12))
+        .map(|number| {
+            let spread = distance * rng.gen_range(0.9..1.1);
+            SolarBodyDescriptor {
+                name: format!("{name} {number}"),
+                size: round(rng.gen_range(3.0..9.0), 1),
+                orbit: Some(orbit(rng, class, spread, 0.0..0.2)),
+                tint: Some(color(ROCKY_TINTS[0])),
+                image: None,
+                dialogue: None,
+                children: vec![],
+            }
+        })
+        .collect()
+}
+
+fn comet(
+    rng: &mut ChaCha8Rng,
+    names: &mut NameGenerator,
+    class: &StarClass,
+    outermost: f32,
+) -> SolarBodyDescriptor {
+    let distance = outermost * rng.gen_range(0.5..0.9);
+    let mut orbit = orbit(rng, class, distance, 0.6..0.92);
+    orbit.retrograde = rng.gen_bool(0.3);
+    SolarBodyDescriptor {
+        name: names.next(rng),
+        size: round(rng.gen_range(3.0..6.0), 1),
+        orbit: Some(orbit),
+        tint: Some(color([0.75, 0.9, 1.0])),
+        image: None,
+        dialogue: None,
+        children: vec![],
+    }
+}
+
+fn orbit(
+    rng: &mut ChaCha8Rng,
+    class: &StarClass,
+    distance: f32,
+    eccentricity: std::ops::Range<f32>,
+) -> OrbitalBody {
+    let period = KEPLER_PERIOD * (distance / KEPLER_DISTANCE).powf(1.5) / class.mass.sqrt();
+    OrbitalBody {
+        distance: round(distance, 0),
+        period: Some(round(period, 0).max(1.0)),
+        start: angle(rng),
+        eccentricity: round(rng.gen_range(eccentricity), 3),
+        periapsis: angle(rng),
+        retrograde: false,
+    }
+}
+
+fn angle(rng: &mut ChaCha8Rng) -> f32 {
+    round(rng.gen_range(0.0..std::f32::consts::TAU), 3)
+}
+
+fn color([red, green, blue]: [f32; 3]) -> Color {
+    Color::srgb(red, green, blue)
+}
+
+/// Rounds to `places` decimal places, so the JSON is easy to read and edit.
+fn round(value: f32, places: i32) -> f32 {
+    let scale = 10f32.powi(places);
+    (value * scale).round() / scale
+}
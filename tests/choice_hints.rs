@@ -0,0 +1,60 @@
+use star_explorer_lib::localization::dialogue_keys;
+use star_explorer_lib::story_system::{Dialogue, GameFlags};
+
+const AIRLOCK: &str = r#"{
+    "entry": "airlock",
+    "nodes": [
+        {
+            "id": "airlock",
+            "texts": [{ "text": "The airlock is sealed." }],
+            "choices": [
+                {
+                    "text": "Override the lock.",
+                    "next": "open",
+                    "condition": "has_codes",
+                    "hint": "@airlock.needs_codes"
+                },
+                { "text": "Cut through.", "next": "open", "condition": "has_torch" },
+                { "text": "Leave.", "next": "airlock" }
+            ]
+        },
+        { "id": "open", "texts": [{ "text": "It opens." }], "choices": [] }
+    ]
+}"#;
+
+fn listed(dialogue: &Dialogue, flags: &GameFlags) -> Vec<(String, bool)> {
+    dialogue
+        .choice_list("airlock", flags)
+        .into_iter()
+        .map(|(choice, enabled)| (choice.text, enabled))
+        .collect()
+}
+
+#[test]
+fn failing_choices_with_hints_are_listed_disabled() {
+    let dialogue: Dialogue = serde_json::from_str(AIRLOCK).unwrap();
+    let mut flags = GameFlags::default();
+    assert_eq!(
+        listed(&dialogue, &flags),
+        [
+            ("Override the lock.".to_string(), false),
+            ("Leave.".to_string(), true)
+        ]
+    );
+    assert_eq!(dialogue.get_choices("airlock", &flags).unwrap().len(), 1);
+
+    flags.set("has_codes");
+    assert_eq!(
+        listed(&dialogue, &flags),
+        [
+            ("Override the lock.".to_string(), true),
+            ("Leave.".to_string(), true)
+        ]
+    );
+}
+
+#[test]
+fn hints_are_string_keys_too() {
+    let dialogue: Dialogue = serde_json::from_str(AIRLOCK).unwrap();
+    assert!(dialogue_keys(&dialogue).contains(&("airlock", "airlock.needs_codes")));
+}
@@ -0,0 +1,91 @@
+use star_explorer_lib::communication_system::{
+    CHOICES_PER_PAGE, ChoiceLine, choice_page, page_lines,
+};
+
+/// `count` choices, where the ones in `greyed` can't be taken.
+fn lines(count: usize, greyed: &[usize]) -> Vec<ChoiceLine> {
+    let mut next = 0;
+    (0..count)
+        .map(|n| ChoiceLine {
+            label: format!("choice {n}"),
+            index: (!greyed.contains(&n)).then(|| {
+                next += 1;
+                next - 1
+            }),
+        })
+        .collect()
+}
+
+fn labels(lines: &[ChoiceLine]) -> Vec<&str> {
+    lines.iter().map(|line| line.label.as_str()).collect()
+}
+
+#[test]
+fn a_page_has_a_digit_for_each_choice() {
+    assert_eq!(CHOICES_PER_PAGE, 9);
+    let shown = page_lines(lines(9, &[]), 0);
+    assert_eq!(shown.len(), 9);
+    assert_eq!(shown[0].label, "1. choice 0");
+    assert_eq!(shown[8].label, "9. choice 8");
+}
+
+#[test]
+fn pages_follow_the_selection() {
+    let list = lines(20, &[]);
+    let index = |line: &ChoiceLine| line.index;
+    assert_eq!(choice_page(&list, index, 0), 0..9);
+    assert_eq!(choice_page(&list, index, 8), 0..9);
+    assert_eq!(choice_page(&list, index, 9), 9..18);
+    assert_eq!(choice_page(&list, index, 19), 18..20);
+    // An index past the end falls back to the first page.
+    assert_eq!(choice_page(&list, index, 40), 0..9);
+    assert_eq!(choice_page(&[] as &[ChoiceLine], index, 0), 0..0);
+}
+
+#[test]
+fn greyed_choices_take_space_but_not_digits() {
+    // Choice 2 is greyed out, so the choice listed 10th is the 9th that can be taken.
+    let list = lines(12, &[2]);
+    let index = |line: &ChoiceLine| line.index;
+    assert_eq!(choice_page(&list, index, 8), 9..12);
+    assert_eq!(choice_page(&list, index, 7), 0..9);
+
+    let shown = page_lines(list.clone(), 0);
+    assert_eq!(
+        labels(&shown),
+        [
+            "1. choice 0",
+            "2. choice 1",
+            "choice 2",
+            "3. choice 3",
+            "4. choice 4",
+            "5. choice 5",
+            "6. choice 6",
+            "7. choice 7",
+            "8. choice 8",
+            "(more choices below)",
+        ]
+    );
+    assert_eq!(shown[2].index, None);
+
+    let shown = page_lines(list, 10);
+    assert_eq!(
+        labels(&shown),
+        [
+            "(more choices above)",
+            "1. choice 9",
+            "2. choice 10",
+            "3. choice 11",
+        ]
+    );
+    assert_eq!(shown[1].index, Some(8));
+}
+
+#[test]
+fn middle_pages_note_both_ways() {
+    let shown = page_lines(lines(30, &[]), 12);
+    assert_eq!(shown.len(), CHOICES_PER_PAGE + 2);
+    assert_eq!(shown[0].label, "(more choices above)");
+    assert_eq!(shown[1].label, "1. choice 9");
+    assert_eq!(shown[CHOICES_PER_PAGE + 1].label, "(more choices below)");
+}
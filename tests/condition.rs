@@ -0,0 +1,105 @@
+use star_explorer_lib::condition::{Condition, Value};
+use star_explorer_lib::story_system::GameFlags;
+
+fn flags(set: &[&str]) -> GameFlags {
+    let mut flags = GameFlags::default();
+    for flag in set {
+        flags.set(flag);
+    }
+    flags
+}
+
+fn holds(source: &str, flags: &GameFlags) -> bool {
+    Condition::parse(source)
+        .unwrap_or_else(|e| panic!("{e}"))
+        .evaluate(flags)
+}
+
+#[test]
+fn and_binds_tighter_than_or() {
+    let only_a = flags(&["a"]);
+    assert!(holds("a || b && c", &only_a));
+    assert!(holds("b && c || a", &only_a));
+    assert!(!holds("(a || b) && c", &only_a));
+    assert!(holds("a || b && c || d", &only_a));
+}
+
+#[test]
+fn not_binds_tighter_than_and() {
+    let nothing = flags(&[]);
+    assert!(!holds("!a && b", &nothing));
+    assert!(holds("!(a && b)", &nothing));
+    assert!(holds("!a || b", &nothing));
+    assert!(holds("!!!a", &nothing));
+    assert!(!holds("!!a", &nothing));
+}
+
+#[test]
+fn parentheses_group() {
+    let both = flags(&["a", "b"]);
+    assert!(!holds("!(a && b)", &both));
+    assert!(holds("((a) && (b || c))", &both));
+    assert!(!holds("a && !(b || c)", &both));
+    assert!(holds("", &both));
+    assert!(holds("  ", &both));
+}
+
+#[test]
+fn numbers_compare_against_variables() {
+    let mut state = GameFlags::default();
+    state.set_var("credits", Value::Int(150));
+    state.set_var("reputation", Value::Float(2.5));
+    assert!(holds("credits >= 150", &state));
+    assert!(holds("credits > 100 && credits < 200", &state));
+    assert!(!holds("credits == 100", &state));
+    assert!(holds("credits != 100", &state));
+    assert!(holds("credits <= 150.5", &state));
+    assert!(holds("reputation > 2", &state));
+    assert!(holds("reputation > -1.5", &state));
+    assert!(holds("-5 < reputation", &state));
+    // Unset variables read as false, which compares as 0.
+    assert!(holds("cargo == 0", &state));
+    assert!(holds("cargo < 1", &state));
+}
+
+#[test]
+fn strings_compare_against_variables() {
+    let mut state = GameFlags::default();
+    state.set_var("faction", Value::Str("miners".to_string()));
+    assert!(holds(r#"faction == "miners""#, &state));
+    assert!(holds(r#"faction != "pirates""#, &state));
+    assert!(holds(r#"faction < "pirates""#, &state));
+    assert!(holds(r#""miners" == faction && !has_job"#, &state));
+    assert!(holds(
+        r#"motto == "say \"hi\"" || faction == "miners""#,
+        &state
+    ));
+    // Strings and numbers are never equal.
+    assert!(!holds("faction == 0", &state));
+    assert!(!holds("faction > 0", &state));
+}
+
+#[test]
+fn errors_point_at_the_problem() {
+    let cases = [
+        ("a & b", "expected `&&`", 2..3),
+        ("a | b", "expected `||`", 2..3),
+        ("a &&& b", "expected `&&`", 4..5),
+        ("a = b", "expected `==`", 2..3),
+        ("(a && b", "expected `)`", 7..7),
+        ("a b", "expected `&&`, `||` or end of condition", 2..3),
+        ("a &&", "expected a flag or value", 4..4),
+        (r#"name == "open"#, "unterminated string", 8..13),
+        ("credits > 1.2.3", "invalid number", 10..15),
+        ("a && $", "unexpected character `$`", 5..6),
+    ];
+    for (source, message, span) in cases {
+        let error = Condition::parse(source).unwrap_err();
+        assert_eq!(
+            (error.message.as_str(), error.span.clone()),
+            (message, span),
+            "{source}"
+        );
+        assert_eq!(error.source, source);
+    }
+}
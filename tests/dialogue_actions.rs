@@ -0,0 +1,123 @@
+use bevy::asset::LoadState;
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+use star_explorer_lib::dialogue_actions::{
+    Action, ActionCall, ActionRegistry, ArgType, DialogueActionsAppExt, perform_action,
+    perform_actions,
+};
+use star_explorer_lib::story_system::{Dialogue, GameFlags, StoryPlugin};
+use std::time::Duration;
+
+fn action(source: &str) -> Action {
+    Action::parse(source).unwrap()
+}
+
+#[test]
+fn last_argument_keeps_colons() {
+    let registry = ActionRegistry::default();
+    let set = action(r#"set:motto:"a:b""#);
+    registry.validate(&set).unwrap();
+    assert_eq!(set.args(2).str(0), "motto");
+    assert_eq!(set.args(2).str(1), r#""a:b""#);
+    assert_eq!(set.to_string(), r#"set:motto:"a:b""#);
+
+    let mut flags = GameFlags::default();
+    assert!(perform_action(&set, &mut flags));
+    assert_eq!(flags.value("motto").to_string(), "a:b");
+    assert!(perform_action(&action("set_flag:dock:7"), &mut flags));
+    assert!(flags.is_set("dock:7"));
+
+    let error = registry.validate(&action("add:credits:5:6")).unwrap_err();
+    assert!(error.contains("expects a number for argument 2"), "{error}");
+    let error = registry.validate(&action("set:motto")).unwrap_err();
+    assert!(error.contains("takes 2 argument(s), found 1"), "{error}");
+}
+
+#[derive(Resource, Debug, PartialEq)]
+struct Given {
+    item: String,
+    count: i64,
+    speaker: Option<Entity>,
+}
+
+fn give(world: &mut World, call: &ActionCall) {
+    world.insert_resource(Given {
+        item: call.args.str(0).to_string(),
+        count: call.args.int(1),
+        speaker: call.speaker,
+    });
+}
+
+/// A game that loads dialogues from `tests/dialogues` and has a `give:<item>:<count>`
+/// action.
+fn app() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin {
+            file_path: "tests/dialogues".to_string(),
+            ..default()
+        },
+    ));
+    app.init_asset::<Font>();
+    app.add_plugins(StoryPlugin);
+    app.register_dialogue_action("give", &[ArgType::Str, ArgType::Int], give);
+    app
+}
+
+#[test]
+fn registered_actions_run_with_the_world() {
+    let mut app = app();
+    let depot = app.world_mut().spawn_empty().id();
+    app.world_mut()
+        .run_system_once(move |mut commands: Commands| {
+            let mut flags = GameFlags::default();
+            let actions = [action("set_flag:refuelled"), action("give:fuel cell:3")];
+            perform_actions(&actions, &mut flags, &mut commands, Some(depot));
+            assert!(flags.is_set("refuelled"));
+        })
+        .unwrap();
+    assert_eq!(
+        app.world().get_resource::<Given>(),
+        Some(&Given {
+            item: "fuel cell".to_string(),
+            count: 3,
+            speaker: Some(depot),
+        })
+    );
+}
+
+#[test]
+#[should_panic(expected = "dialogue action `set` is already registered")]
+fn built_in_actions_cant_be_replaced() {
+    app().register_dialogue_action("set", &[ArgType::Str, ArgType::Any], give);
+}
+
+#[test]
+fn unregistered_actions_fail_to_load() {
+    let mut app = app();
+    let server = app.world().resource::<AssetServer>().clone();
+    let refuel = server.load::<Dialogue>("refuel.json");
+    let unknown = server.load::<Dialogue>("unknown_action.json");
+    let settled = |server: &AssetServer, handle: &Handle<Dialogue>| {
+        matches!(
+            server.get_load_state(handle),
+            Some(LoadState::Loaded | LoadState::Failed(_))
+        )
+    };
+    for _ in 0..500 {
+        app.update();
+        if settled(&server, &refuel) && settled(&server, &unknown) {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert!(server.is_loaded(&refuel));
+    let Some(LoadState::Failed(error)) = server.get_load_state(&unknown) else {
+        panic!("unknown_action.json loaded");
+    };
+    assert!(
+        error.to_string().contains("unknown action `refuel:3`"),
+        "{error}"
+    );
+}
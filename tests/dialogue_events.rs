@@ -0,0 +1,147 @@
+use bevy::ecs::event::Event;
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+use star_explorer_lib::story_system::{
+    ActiveDialogue, ChoiceSelected, Dialogue, DialogueEnded, DialogueStarted, FlagChanged,
+    GameFlags, NodeEntered, StoryPlugin,
+};
+
+const BEACON: &str = r#"{
+    "entry": "start",
+    "nodes": [
+        {
+            "id": "start",
+            "texts": [{ "text": "Beacon online." }],
+            "choices": [
+                { "text": "Listen.", "next": "message" },
+                { "text": "Take the job.", "next": "end", "actions": ["set_flag:has_job"] }
+            ],
+            "on_enter": ["set_flag:heard_beacon"]
+        },
+        {
+            "id": "message",
+            "texts": [{ "text": "Help wanted." }],
+            "choices": [{ "text": "Back.", "next": "start" }]
+        }
+    ]
+}"#;
+
+fn app() -> (App, Entity, Handle<Dialogue>) {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default()));
+    app.init_asset::<Font>();
+    app.add_plugins(StoryPlugin);
+    app.update();
+    let dialogue: Dialogue = serde_json::from_str(BEACON).unwrap();
+    let handle = app
+        .world_mut()
+        .resource_mut::<Assets<Dialogue>>()
+        .add(dialogue);
+    let beacon = app.world_mut().spawn_empty().id();
+    (app, beacon, handle)
+}
+
+fn hail(app: &mut App, speaker: Entity, handle: Handle<Dialogue>) {
+    app.world_mut()
+        .run_system_once(
+            move |mut commands: Commands,
+                  mut active: ResMut<ActiveDialogue>,
+                  mut flags: ResMut<GameFlags>,
+                  dialogues: Res<Assets<Dialogue>>| {
+                let dialogue = dialogues.get(&handle).unwrap();
+                active.set_active(&handle, dialogue, speaker, &mut flags, &mut commands);
+            },
+        )
+        .unwrap();
+    app.update();
+}
+
+fn choose(app: &mut App, index: usize) {
+    app.world_mut()
+        .run_system_once(
+            move |mut commands: Commands,
+                  mut active: ResMut<ActiveDialogue>,
+                  mut flags: ResMut<GameFlags>| {
+                assert!(active.choose(index, &mut flags, &mut commands));
+            },
+        )
+        .unwrap();
+    app.update();
+}
+
+fn sent<E: Event>(app: &mut App) -> Vec<E> {
+    app.world_mut()
+        .resource_mut::<Events<E>>()
+        .drain()
+        .collect()
+}
+
+fn flag_names(changes: &[FlagChanged]) -> Vec<(&str, Option<Entity>)> {
+    changes
+        .iter()
+        .map(|change| (change.name.as_str(), change.speaker))
+        .collect()
+}
+
+#[test]
+fn conversations_send_lifecycle_events() {
+    let (mut app, beacon, handle) = app();
+    hail(&mut app, beacon, handle);
+    let started = sent::<DialogueStarted>(&mut app);
+    assert_eq!(started.len(), 1);
+    assert_eq!(
+        (started[0].speaker, started[0].node.as_str()),
+        (beacon, "start")
+    );
+    let entered = sent::<NodeEntered>(&mut app);
+    assert_eq!(entered.len(), 1);
+    assert_eq!(entered[0].node, "start");
+    assert_eq!(
+        flag_names(&sent::<FlagChanged>(&mut app)),
+        [("heard_beacon", Some(beacon))]
+    );
+
+    choose(&mut app, 0);
+    let selected = sent::<ChoiceSelected>(&mut app);
+    assert_eq!(selected.len(), 1);
+    assert_eq!(
+        (
+            selected[0].node.as_str(),
+            selected[0].index,
+            selected[0].next.as_str()
+        ),
+        ("start", 0, "message")
+    );
+    let entered = sent::<NodeEntered>(&mut app);
+    assert_eq!(entered.len(), 1);
+    assert_eq!(entered[0].node, "message");
+    assert!(sent::<DialogueEnded>(&mut app).is_empty());
+}
+
+#[test]
+fn ending_choices_credit_their_flags_to_the_speaker() {
+    let (mut app, beacon, handle) = app();
+    hail(&mut app, beacon, handle);
+    sent::<FlagChanged>(&mut app);
+
+    choose(&mut app, 1);
+    assert!(app.world().resource::<ActiveDialogue>().entity.is_none());
+    let ended = sent::<DialogueEnded>(&mut app);
+    assert_eq!(ended.len(), 1);
+    assert_eq!(
+        (ended[0].speaker, ended[0].node.as_str()),
+        (beacon, "start")
+    );
+    assert_eq!(
+        flag_names(&sent::<FlagChanged>(&mut app)),
+        [("has_job", Some(beacon))]
+    );
+
+    // Changes made outside a conversation have no speaker.
+    app.world_mut().resource_mut::<GameFlags>().set("outside");
+    app.update();
+    assert_eq!(
+        flag_names(&sent::<FlagChanged>(&mut app)),
+        [("outside", None)]
+    );
+}
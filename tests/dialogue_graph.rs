@@ -0,0 +1,93 @@
+use star_explorer_lib::dialogue_graph::{GraphFormat, dialogue_graph};
+use star_explorer_lib::story_system::Dialogue;
+use std::fs;
+
+const GATE: &str = r#"{
+    "entry": "start",
+    "nodes": [
+        {
+            "id": "start",
+            "texts": [{ "text": "Halt! Who goes there?" }],
+            "choices": [
+                { "text": "A friend.", "next": "pass", "condition": "has_pass" },
+                { "text": "Leave.", "next": "end" },
+                { "text": "Sneak in.", "next": "cells" }
+            ]
+        },
+        {
+            "id": "pass",
+            "texts": [{ "text": "Go on \"through\"." }],
+            "choices": [{ "text": "Thanks.", "next": "end", "actions": ["set_flag:inside"] }]
+        }
+    ]
+}"#;
+
+fn gate() -> Dialogue {
+    serde_json::from_str(GATE).unwrap()
+}
+
+fn lines(graph: &str) -> Vec<&str> {
+    graph.lines().map(|line| line.trim()).collect()
+}
+
+#[test]
+fn dot_lists_nodes_and_choices() {
+    let graph = dialogue_graph(&gate(), GraphFormat::Dot);
+    assert_eq!(
+        lines(&graph),
+        [
+            "digraph dialogue {",
+            "node [shape=box];",
+            r#"n0 [label="start\nHalt! Who goes there?", penwidth=2];"#,
+            r#"n1 [label="pass\nGo on \"through\"."];"#,
+            r#"missing0 [label="cells\n(missing)", style=dashed];"#,
+            r#"node_end [label="end", shape=doublecircle];"#,
+            r#"n0 -> n1 [label="A friend.\n[has_pass]"];"#,
+            r#"n0 -> node_end [label="Leave."];"#,
+            r#"n0 -> missing0 [label="Sneak in."];"#,
+            r#"n1 -> node_end [label="Thanks.\n/ set_flag:inside"];"#,
+            "}",
+        ]
+    );
+}
+
+#[test]
+fn mermaid_lists_nodes_and_choices() {
+    let graph = dialogue_graph(&gate(), GraphFormat::Mermaid);
+    assert_eq!(
+        lines(&graph),
+        [
+            "flowchart TD",
+            r#"n0["start<br/>Halt! Who goes there?"]"#,
+            r#"n1["pass<br/>Go on #quot;through#quot;."]"#,
+            r#"missing0["cells (missing)"]"#,
+            r#"node_end(("end"))"#,
+            r#"n0 -->|"A friend.<br/>[has_pass]"| n1"#,
+            r#"n0 -->|"Leave."| node_end"#,
+            r#"n0 -->|"Sneak in."| missing0"#,
+            r#"n1 -->|"Thanks.<br/>/ set_flag:inside"| node_end"#,
+            "style n0 stroke-width:3px",
+            "style missing0 stroke-dasharray:5",
+        ]
+    );
+}
+
+// `end` is a keyword in Mermaid, so no line may use it as an id.
+#[test]
+fn bundled_dialogues_avoid_mermaid_keywords() {
+    for entry in fs::read_dir("assets/dialogue").unwrap() {
+        let path = entry.unwrap().path();
+        let dialogue: Dialogue = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let graph = dialogue_graph(&dialogue, GraphFormat::Mermaid);
+        for line in lines(&graph) {
+            // The id that starts a line, and the target of an edge.
+            let first = line.split(['[', '(', ' ']).next();
+            let target = line.rsplit_once("| ").map(|(_, to)| to);
+            assert!(
+                first != Some("end") && target != Some("end"),
+                "{}: {line}",
+                path.display()
+            );
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use star_explorer_lib::dialogue_actions::ActionRegistry;
+use star_explorer_lib::dialogue_lint::{
+    LintIssue, Severity, lint_actions, lint_dialogue_dir, lint_dialogues,
+};
+use star_explorer_lib::story_system::Dialogue;
+use std::path::{Path, PathBuf};
+
+fn dialogues(sources: &[(&str, &str)]) -> Vec<(PathBuf, Dialogue)> {
+    sources
+        .iter()
+        .map(|(file, json)| (PathBuf::from(file), serde_json::from_str(json).unwrap()))
+        .collect()
+}
+
+/// The `(node, message)` of every issue, to compare without the file names.
+fn messages(issues: &[LintIssue]) -> Vec<(Option<&str>, &str)> {
+    issues
+        .iter()
+        .map(|issue| (issue.node.as_deref(), issue.message.as_str()))
+        .collect()
+}
+
+#[test]
+fn bundled_dialogues_have_no_errors() {
+    let issues = lint_dialogue_dir(Path::new("assets/dialogue"), &ActionRegistry::default());
+    let errors = issues
+        .iter()
+        .filter(|issue| issue.is_error())
+        .collect::<Vec<_>>();
+    assert!(errors.is_empty(), "{errors:#?}");
+}
+
+#[test]
+fn dead_ends_are_nodes_that_cant_reach_the_end() {
+    let dialogue = dialogues(&[(
+        "loop.json",
+        r#"{
+            "entry": "start",
+            "nodes": [
+                {
+                    "id": "start",
+                    "texts": [{ "text": "Hello." }],
+                    "choices": [
+                        { "text": "Chat.", "next": "chat" },
+                        { "text": "Bye.", "next": "end" }
+                    ]
+                },
+                {
+                    "id": "chat",
+                    "texts": [{ "text": "Nice weather." }],
+                    "choices": [{ "text": "Back.", "next": "start" }]
+                },
+                {
+                    "id": "trap",
+                    "texts": [{ "text": "No way out." }],
+                    "choices": [{ "text": "Again.", "next": "maze" }]
+                },
+                {
+                    "id": "maze",
+                    "texts": [{ "text": "Still no way out." }],
+                    "choices": [{ "text": "Again.", "next": "trap" }]
+                },
+                { "id": "silent", "texts": [{ "text": "..." }], "choices": [] }
+            ]
+        }"#,
+    )]);
+    let issues = lint_dialogues(&dialogue);
+    let dead_ends = messages(&issues)
+        .into_iter()
+        .filter(|(_, message)| message.starts_with("dead end"))
+        .map(|(node, _)| node.unwrap())
+        .collect::<Vec<_>>();
+    // `chat` has no ending choice of its own, but can get back to one.
+    assert_eq!(dead_ends, ["trap", "maze", "silent"]);
+}
+
+#[test]
+fn missing_nodes_and_entries_are_reported() {
+    let dialogue = dialogues(&[(
+        "broken.json",
+        r#"{
+            "entry": "hello",
+            "nodes": [
+                {
+                    "id": "start",
+                    "texts": [{ "text": "Hello." }],
+                    "choices": [
+                        { "text": "Go.", "next": "nowhere" },
+                        { "text": "Bye.", "next": "end", "actions": ["resume:later"] }
+                    ]
+                }
+            ]
+        }"#,
+    )]);
+    let issues = lint_dialogues(&dialogue);
+    assert_eq!(
+        messages(&issues),
+        [
+            (None, "entry node `hello` doesn't exist"),
+            (
+                Some("start"),
+                "choice `Go.` leads to missing node `nowhere`"
+            ),
+            (
+                Some("start"),
+                "`resume:later` resumes at missing node `later`"
+            ),
+            (Some("start"), "unreachable from entry `hello`"),
+        ]
+    );
+    assert!(
+        issues
+            .iter()
+            .all(|issue| issue.file == Path::new("broken.json"))
+    );
+}
+
+#[test]
+fn flags_must_be_set_somewhere() {
+    let dialogue = dialogues(&[
+        (
+            "reader.json",
+            r#"{
+                "entry": "start",
+                "nodes": [
+                    {
+                        "id": "start",
+                        "texts": [{ "text": "Hello." }],
+                        "choices": [
+                            { "text": "Job?", "next": "end", "condition": "has_job" },
+                            { "text": "Rich?", "next": "end", "condition": "credits > 100" },
+                            { "text": "Lost?", "next": "end", "condition": "lost_cargo" }
+                        ]
+                    }
+                ]
+            }"#,
+        ),
+        (
+            "writer.json",
+            r#"{
+                "entry": "start",
+                "nodes": [
+                    {
+                        "id": "start",
+                        "texts": [{ "text": "Work?" }],
+                        "choices": [
+                            { "text": "Sure.", "next": "end", "actions": ["set_flag:has_job", "add:credits:50"] }
+                        ]
+                    }
+                ]
+            }"#,
+        ),
+    ]);
+    // Flags and variables set by another dialogue count.
+    let issues = lint_dialogues(&dialogue);
+    assert_eq!(
+        messages(&issues),
+        [(
+            Some("start"),
+            "`lost_cargo` is read by a condition but never set"
+        )]
+    );
+    assert_eq!(issues[0].file, Path::new("reader.json"));
+}
+
+#[test]
+fn unknown_actions_are_warnings() {
+    let dialogue = dialogues(&[(
+        "depot.json",
+        r#"{
+            "entry": "start",
+            "nodes": [
+                {
+                    "id": "start",
+                    "texts": [{ "text": "Fuel?" }],
+                    "choices": [
+                        { "text": "Yes.", "next": "end", "actions": ["refuel:3", "add:credits:lots"] }
+                    ]
+                }
+            ]
+        }"#,
+    )]);
+    let issues = lint_actions(&dialogue, &ActionRegistry::default());
+    let severities = issues
+        .iter()
+        .map(|issue| issue.severity)
+        .collect::<Vec<_>>();
+    assert_eq!(severities, [Severity::Warning, Severity::Error]);
+    assert!(issues[0].message.contains("refuel:3"), "{}", issues[0]);
+    assert!(issues[0].to_string().starts_with("warning: "));
+}
+
+#[test]
+fn registered_actions_before_built_in_ones_are_warnings() {
+    let dialogue = dialogues(&[(
+        "dock.json",
+        r#"{
+            "entry": "start",
+            "nodes": [
+                {
+                    "id": "start",
+                    "texts": [{ "text": "Docked." }],
+                    "choices": [
+                        { "text": "Later.", "next": "end", "actions": ["resume:start", "set_flag:met"] },
+                        { "text": "Bye.", "next": "end", "actions": ["set_flag:met", "resume:start"] }
+                    ],
+                    "on_exit": ["resume:start", "resume:start"]
+                }
+            ]
+        }"#,
+    )]);
+    let issues = lint_actions(&dialogue, &ActionRegistry::default());
+    assert_eq!(
+        messages(&issues),
+        [(
+            Some("start"),
+            "`resume:start` runs after the built-in actions listed after it"
+        )]
+    );
+    assert_eq!(issues[0].severity, Severity::Warning);
+}
@@ -0,0 +1,49 @@
+use star_explorer_lib::dialogue_actions::{Action, perform_action};
+use star_explorer_lib::dialogue_template::{TemplateContext, placeholders, render};
+use star_explorer_lib::story_system::GameFlags;
+
+fn context(flags: &GameFlags) -> TemplateContext<'_> {
+    TemplateContext {
+        flags,
+        speaker: Some("Earth"),
+        ship: None,
+    }
+}
+
+#[test]
+fn fills_in_variables_flags_and_speaker() {
+    let mut flags = GameFlags::default();
+    flags.set("has_job");
+    let action: Action = serde_json::from_str("\"add:credits:500\"").unwrap();
+    assert!(perform_action(&action, &mut flags));
+    let text = render(
+        "{speaker}: Your reward is {credits} credits, {player_name}. Job: {flag.has_job}",
+        &context(&flags),
+    )
+    .unwrap();
+    assert_eq!(
+        text,
+        "Earth: Your reward is 500 credits, Captain. Job: true"
+    );
+}
+
+#[test]
+fn doubled_braces_are_literal() {
+    let flags = GameFlags::default();
+    assert_eq!(
+        render("{{not a placeholder}} {{{player_name}}}", &context(&flags)).unwrap(),
+        "{not a placeholder} {Captain}"
+    );
+}
+
+#[test]
+fn reports_bad_placeholders() {
+    let flags = GameFlags::default();
+    let error = render("Hello {nobody}", &context(&flags)).unwrap_err();
+    assert_eq!(error.message, "unknown placeholder `nobody`");
+    assert_eq!(error.span, 6..14);
+    assert!(render("{ship.speed}", &context(&flags)).is_err());
+    assert!(placeholders("Hello {name").is_err());
+    assert!(placeholders("Hello }").is_err());
+    assert!(placeholders("{}").is_err());
+}
@@ -0,0 +1,90 @@
+use star_explorer_lib::dialogue_actions::ActionRegistry;
+use star_explorer_lib::dialogue_lint::load_dialogue;
+use star_explorer_lib::dialogue_runner::{DialogueRunner, PlaythroughScript};
+use star_explorer_lib::localization::{LOCALE_DIR, Localization};
+use star_explorer_lib::story_system::{Dialogue, GameFlags};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+fn load_dialogues() -> HashMap<String, Dialogue> {
+    let registry = ActionRegistry::default();
+    ["earth", "mars"]
+        .into_iter()
+        .map(|name| {
+            let path = Path::new("assets/dialogue").join(format!("{name}.json"));
+            let dialogue = load_dialogue(&path, &registry).unwrap();
+            (name.to_string(), dialogue)
+        })
+        .collect()
+}
+
+fn load_strings() -> Localization {
+    Localization::load(Path::new(LOCALE_DIR)).unwrap()
+}
+
+fn load_script(name: &str) -> PlaythroughScript {
+    let json = fs::read_to_string(Path::new("tests/scripts").join(name)).unwrap();
+    serde_json::from_str(&json).unwrap()
+}
+
+#[test]
+fn earth_mars_delivery() {
+    let dialogues = load_dialogues();
+    let strings = load_strings();
+    let mut flags = GameFlags::default();
+    load_script("earth_mars_delivery.json")
+        .run(&dialogues, &strings, &mut flags)
+        .unwrap();
+    assert!(!flags.is_set("has_job"));
+    assert!(!flags.is_set("has_package"));
+}
+
+#[test]
+fn failed_scripts_leave_flags_alone() {
+    let dialogues = load_dialogues();
+    let strings = load_strings();
+    let mut flags = GameFlags::default();
+    flags.set("veteran");
+    let script: PlaythroughScript = serde_json::from_str(
+        r#"{
+            "steps": [
+                { "start": "earth" },
+                { "choose": 0 },
+                { "choose": 0 },
+                { "expect_node": "goodbye" }
+            ]
+        }"#,
+    )
+    .unwrap();
+    let error = script.run(&dialogues, &strings, &mut flags).unwrap_err();
+    assert_eq!(error.step, 3);
+    assert!(flags.is_set("veteran"));
+    assert!(!flags.is_set("has_job"));
+}
+
+#[test]
+fn mars_ignores_courier_without_package() {
+    let dialogues = load_dialogues();
+    let strings = load_strings();
+    let mut runner =
+        DialogueRunner::start(&dialogues["mars"], GameFlags::default()).with_strings(&strings);
+    assert!(runner.text().unwrap().starts_with("Another Earthling"));
+    assert_eq!(runner.choices().len(), 2);
+    runner.choose(0).unwrap();
+    assert_eq!(runner.node_id(), "no_package");
+    assert!(runner.choose(5).is_err());
+    assert!(runner.pending.is_empty());
+}
+
+#[test]
+fn earth_goodbye_closes_the_channel() {
+    let dialogues = load_dialogues();
+    let mut runner = DialogueRunner::start(&dialogues["earth"], GameFlags::default());
+    runner.choose(2).unwrap();
+    assert_eq!(runner.node_id(), "goodbye");
+    assert!(!runner.is_finished());
+    runner.choose(1).unwrap();
+    assert!(runner.is_finished());
+    assert_eq!(runner.text(), None);
+}
@@ -0,0 +1,302 @@
+use bevy::input::InputPlugin;
+use bevy::prelude::*;
+use bevy::sprite::ColorMaterial;
+use star_explorer_lib::GameActions;
+use star_explorer_lib::background_stars::BackgroundStarConfig;
+use star_explorer_lib::galaxy::{
+    ARRIVAL_DISTANCE, GALAXY_FILE, Galaxy, GalaxyMap, GalaxyPlugin, JumpTo, SystemSource,
+};
+use star_explorer_lib::game_clock::{GameClock, GameClockPlugin};
+use star_explorer_lib::input_actions::GameActionsPlugin;
+use star_explorer_lib::player_ship::MyShip;
+use star_explorer_lib::save_system::{
+    LoadGame, SaveData, SaveGame, SaveSlot, SaveSlots, SaveSystemPlugin,
+};
+use star_explorer_lib::solar_system::{SolarBody, SolarSystem, SolarSystemPlugin};
+use star_explorer_lib::space_position::SpacePosition;
+use star_explorer_lib::story_system::{
+    ActiveDialogue, Dialogue, DialogueHandle, GameFlags, StoryPlugin,
+};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+#[test]
+fn bundled_galaxy_is_connected() {
+    let json = fs::read_to_string(Path::new("assets").join(GALAXY_FILE)).unwrap();
+    let galaxy: Galaxy = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        galaxy.system(&galaxy.start).unwrap().source,
+        SystemSource::File("systems/sol.system.json".to_string())
+    );
+    // Every system can be reached from the start.
+    let mut reached = HashSet::from([galaxy.start.as_str()]);
+    let mut frontier = vec![galaxy.start.as_str()];
+    while let Some(id) = frontier.pop() {
+        for next in galaxy.neighbours(id) {
+            if reached.insert(next.id.as_str()) {
+                frontier.push(next.id.as_str());
+            }
+        }
+    }
+    assert_eq!(reached.len(), galaxy.systems.len());
+}
+
+#[test]
+fn targets_are_neighbours_by_name() {
+    let galaxy: Galaxy = serde_json::from_str(
+        r#"{
+            "start": "a",
+            "systems": [
+                { "id": "a", "name": "Alpha", "position": [0, 0], "file": "a.system.json" },
+                { "id": "b", "name": "Zeta", "position": [1, 0], "seed": 1 },
+                { "id": "c", "name": "Beta", "position": [0, 1], "seed": 2 },
+                { "id": "d", "name": "Delta", "position": [1, 1], "seed": 3 }
+            ],
+            "lanes": [["a", "b"], ["c", "a"], ["b", "d"]]
+        }"#,
+    )
+    .unwrap();
+    let names = |id| {
+        galaxy
+            .neighbours(id)
+            .iter()
+            .map(|system| system.name.as_str())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names("a"), ["Beta", "Zeta"]);
+    assert_eq!(names("d"), ["Zeta"]);
+
+    let mut map = GalaxyMap::new(galaxy);
+    assert_eq!(map.target().unwrap().id, "c");
+    map.target = 1;
+    assert_eq!(map.target().unwrap().id, "b");
+    map.target = 2;
+    assert_eq!(map.target().unwrap().id, "c");
+}
+
+#[test]
+fn bad_galaxies_are_rejected() {
+    let error = |json: &str| {
+        serde_json::from_str::<Galaxy>(json)
+            .unwrap_err()
+            .to_string()
+    };
+    assert!(
+        error(r#"{ "start": "a", "systems": [{ "id": "a", "name": "A", "position": [0, 0] }] }"#)
+            .contains("system `a` needs either a `file` or a `seed`")
+    );
+    assert!(
+        error(
+            r#"{
+                "start": "a",
+                "systems": [{ "id": "a", "name": "A", "position": [0, 0], "seed": 1 }],
+                "lanes": [["a", "b"]]
+            }"#
+        )
+        .contains("lane from `a` to `b`: there's no system `b`")
+    );
+    assert!(
+        error(r#"{ "start": "x", "systems": [] }"#).contains("the start system `x` doesn't exist")
+    );
+}
+
+/// The game without a window or renderer, flying around the bundled galaxy.
+fn headless_game() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default(), InputPlugin));
+    app.init_asset::<Image>()
+        .init_asset::<Mesh>()
+        .init_asset::<ColorMaterial>()
+        .init_asset::<Font>();
+    app.add_plugins((
+        GameActionsPlugin::<GameActions>::default(),
+        GameClockPlugin,
+        StoryPlugin,
+        SolarSystemPlugin,
+        GalaxyPlugin,
+        SaveSystemPlugin,
+    ));
+    app.insert_resource(BackgroundStarConfig::default());
+    app.insert_resource(SaveSlots {
+        dir: std::env::temp_dir().join(format!("star_explorer_galaxy_{}", std::process::id())),
+        ..default()
+    });
+    app.world_mut().spawn(Window::default());
+    app.world_mut().spawn((MyShip, SpacePosition(Vec2::ZERO)));
+    app
+}
+
+/// Runs frames until `done`, giving assets time to load.
+fn update_until(app: &mut App, what: &str, done: impl Fn(&mut World) -> bool) {
+    for _ in 0..500 {
+        app.update();
+        if done(app.world_mut()) {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    panic!("timed out waiting for {what}");
+}
+
+fn body(world: &mut World, name: &str) -> Option<Entity> {
+    world
+        .query::<(Entity, &SolarBody)>()
+        .iter(world)
+        .find(|(_, body)| body.name == name)
+        .map(|(entity, _)| entity)
+}
+
+fn in_system(world: &mut World, id: &str) -> bool {
+    let spawned = world.resource::<SolarSystem>().spawned;
+    world.resource::<GalaxyMap>().current == id
+        && spawned == Some(world.resource::<SolarSystem>().handle.id())
+}
+
+fn jump(app: &mut App, system: &str) {
+    app.world_mut().send_event(JumpTo {
+        system: system.to_string(),
+        arrival: None,
+    });
+    update_until(app, system, |world| in_system(world, system));
+}
+
+fn ship(world: &mut World) -> Vec2 {
+    world
+        .query_filtered::<&SpacePosition, With<MyShip>>()
+        .single(world)
+        .0
+}
+
+#[test]
+fn jumps_carry_the_game_between_systems() {
+    let mut app = headless_game();
+    update_until(&mut app, "sol", |world| {
+        in_system(world, "sol") && body(world, "Earth").is_some()
+    });
+    let earth = body(app.world_mut(), "Earth").unwrap();
+    {
+        let world = app.world_mut();
+        world.resource_mut::<GameFlags>().set("has_job");
+        let mut config = world.resource_mut::<BackgroundStarConfig>();
+        config.speed = 20.0;
+        config.direction = 2.0;
+        let mut dialogue = world.resource_mut::<ActiveDialogue>();
+        dialogue.node_id.insert(earth, "job_details".to_string());
+        dialogue
+            .visited
+            .insert(earth, HashSet::from(["start".to_string()]));
+    }
+    let ticks = app.world().resource::<GameClock>().ticks;
+
+    jump(&mut app, "tigiorex");
+    let world = app.world_mut();
+    assert!(world.get_entity(earth).is_err());
+    assert_eq!(body(world, "Earth"), None);
+    assert!(world.resource::<GameFlags>().is_set("has_job"));
+    let config = world.resource::<BackgroundStarConfig>();
+    assert_eq!((config.speed, config.direction), (20.0, 2.0));
+    assert!(world.resource::<GameClock>().ticks >= ticks);
+    assert!(world.resource::<ActiveDialogue>().node_id.is_empty());
+    let left = &world.resource::<GalaxyMap>().progress["sol"]["Earth"];
+    assert_eq!(left.node.as_deref(), Some("job_details"));
+    // Tigiorex lies towards positive x of Sol, so the ship arrives on that side.
+    let arrival = ship(world);
+    assert!((arrival.length() - ARRIVAL_DISTANCE).abs() < 1.0);
+    assert!(arrival.x < 0.0, "{arrival}");
+
+    jump(&mut app, "sol");
+    update_until(&mut app, "progress", |world| {
+        !world.resource::<GalaxyMap>().progress.contains_key("sol")
+    });
+    let world = app.world_mut();
+    let earth = body(world, "Earth").unwrap();
+    let dialogue = world.resource::<ActiveDialogue>();
+    assert_eq!(dialogue.node_id[&earth], "job_details");
+    assert!(dialogue.visited[&earth].contains("start"));
+}
+
+#[test]
+fn saves_remember_the_system() {
+    let mut app = headless_game();
+    update_until(&mut app, "sol", |world| {
+        in_system(world, "sol") && body(world, "Earth").is_some()
+    });
+    let earth = body(app.world_mut(), "Earth").unwrap();
+    app.world_mut()
+        .resource_mut::<ActiveDialogue>()
+        .resume
+        .insert(earth, "accept_job".to_string());
+    jump(&mut app, "niokio");
+    app.world_mut().resource_mut::<GameFlags>().set("in_niokio");
+    let saved_at = ship(app.world_mut());
+    let slot = SaveSlot::Numbered(1);
+    app.world_mut().send_event(SaveGame(slot));
+    app.update();
+
+    let path = app.world().resource::<SaveSlots>().path(slot);
+    let data = SaveData::read(&path).unwrap();
+    assert_eq!(data.system.as_deref(), Some("niokio"));
+    assert_eq!(
+        data.other_systems["sol"]["Earth"].resume.as_deref(),
+        Some("accept_job")
+    );
+
+    // Loading from elsewhere flies back to where the game was saved.
+    jump(&mut app, "sol");
+    app.world_mut()
+        .resource_mut::<GameFlags>()
+        .remove("in_niokio");
+    app.world_mut().send_event(LoadGame(slot));
+    update_until(&mut app, "niokio", |world| in_system(world, "niokio"));
+    let world = app.world_mut();
+    assert!(world.resource::<GameFlags>().is_set("in_niokio"));
+    assert_eq!(ship(world), saved_at);
+    assert_eq!(
+        world.resource::<GalaxyMap>().progress["sol"]["Earth"]
+            .resume
+            .as_deref(),
+        Some("accept_job")
+    );
+}
+
+#[test]
+fn loads_drop_progress_in_removed_nodes() {
+    let mut app = headless_game();
+    update_until(&mut app, "sol", |world| {
+        in_system(world, "sol") && body(world, "Earth").is_some()
+    });
+    let earth = body(app.world_mut(), "Earth").unwrap();
+    update_until(&mut app, "earth's dialogue", |world| {
+        let handle = world.get::<DialogueHandle>(earth).unwrap().0.id();
+        world.resource::<Assets<Dialogue>>().contains(handle)
+    });
+    {
+        let world = app.world_mut();
+        world.resource_mut::<GameFlags>().set("saved");
+        let mut dialogue = world.resource_mut::<ActiveDialogue>();
+        dialogue.node_id.insert(earth, "cut_scene".to_string());
+        dialogue.resume.insert(earth, "accept_job".to_string());
+        dialogue.visited.insert(
+            earth,
+            HashSet::from(["start".to_string(), "cut_scene".to_string()]),
+        );
+    }
+    let slot = SaveSlot::Numbered(2);
+    app.world_mut().send_event(SaveGame(slot));
+    app.update();
+
+    app.world_mut().resource_mut::<GameFlags>().remove("saved");
+    app.world_mut().send_event(LoadGame(slot));
+    app.update();
+    let world = app.world_mut();
+    assert!(world.resource::<GameFlags>().is_set("saved"));
+    let dialogue = world.resource::<ActiveDialogue>();
+    assert_eq!(dialogue.node_id.get(&earth), None);
+    assert_eq!(dialogue.resume[&earth], "accept_job");
+    assert_eq!(
+        dialogue.visited[&earth],
+        HashSet::from(["start".to_string()])
+    );
+}
@@ -0,0 +1,49 @@
+use star_explorer_lib::game_clock::{GameClock, TICK_SECONDS};
+
+fn run(clock: &mut GameClock, frames: usize, frame_seconds: f64) {
+    for _ in 0..frames {
+        clock.advance(frame_seconds);
+    }
+}
+
+#[test]
+fn ticks_dont_depend_on_frame_rate() {
+    let (mut smooth, mut choppy, mut uneven) = (
+        GameClock::default(),
+        GameClock::default(),
+        GameClock::default(),
+    );
+    run(&mut smooth, 120, 1.0 / 60.0);
+    run(&mut choppy, 20, 0.1);
+    for frame in 0..100 {
+        uneven.advance(if frame % 2 == 0 { 0.005 } else { 0.035 });
+    }
+    for clock in [&smooth, &choppy, &uneven] {
+        assert!((119..=120).contains(&clock.ticks), "{}", clock.ticks);
+    }
+    assert!((smooth.elapsed_secs() - 120.0 * TICK_SECONDS).abs() < 1e-9);
+}
+
+#[test]
+fn warp_and_pause() {
+    let mut clock = GameClock::default();
+    clock.warp_up();
+    clock.warp_up();
+    assert_eq!(clock.warp(), 100);
+    clock.advance(0.1);
+    assert_eq!(clock.ticks, 600);
+    assert!((clock.delta_secs() - 10.0).abs() < 1e-4);
+
+    clock.toggle_pause();
+    clock.advance(0.1);
+    assert_eq!(clock.ticks, 600);
+    assert_eq!(clock.delta_secs(), 0.0);
+
+    for _ in 0..5 {
+        clock.warp_up();
+    }
+    assert_eq!(clock.warp(), 1000);
+    clock.restore(42);
+    assert_eq!((clock.ticks, clock.warp()), (42, 1));
+    assert!(clock.is_paused());
+}
@@ -0,0 +1,74 @@
+use star_explorer_lib::condition::Value;
+use star_explorer_lib::dialogue_actions::{Action, perform_action};
+use star_explorer_lib::story_system::GameFlags;
+
+fn run(flags: &mut GameFlags, source: &str) {
+    assert!(perform_action(&Action::parse(source).unwrap(), flags));
+}
+
+#[test]
+fn set_keeps_each_value_type() {
+    let mut flags = GameFlags::default();
+    run(&mut flags, "set:credits:50");
+    run(&mut flags, "set:ratio:0.5");
+    run(&mut flags, "set:docked:true");
+    run(&mut flags, "set:faction:miners");
+    run(&mut flags, r#"set:motto:"42""#);
+    assert_eq!(flags.var("credits"), Some(&Value::Int(50)));
+    assert_eq!(flags.var("ratio"), Some(&Value::Float(0.5)));
+    assert_eq!(flags.var("docked"), Some(&Value::Bool(true)));
+    assert_eq!(
+        flags.var("faction"),
+        Some(&Value::Str("miners".to_string()))
+    );
+    assert_eq!(flags.var("motto"), Some(&Value::Str("42".to_string())));
+
+    // Names without a variable fall back to the flag of the same name.
+    assert_eq!(flags.value("has_job"), Value::Bool(false));
+    flags.set("has_job");
+    assert_eq!(flags.value("has_job"), Value::Bool(true));
+
+    run(&mut flags, "unset:credits");
+    assert_eq!(flags.var("credits"), None);
+}
+
+#[test]
+fn add_and_sub_do_arithmetic() {
+    let mut flags = GameFlags::default();
+    run(&mut flags, "add:credits:50");
+    assert_eq!(flags.var("credits"), Some(&Value::Int(50)));
+    run(&mut flags, "sub:credits:80");
+    assert_eq!(flags.var("credits"), Some(&Value::Int(-30)));
+    run(&mut flags, "add:credits:0.5");
+    assert_eq!(flags.var("credits"), Some(&Value::Float(-29.5)));
+    run(&mut flags, "sub:credits:0.5");
+    assert_eq!(flags.var("credits"), Some(&Value::Float(-30.0)));
+
+    // Non-numeric variables are left alone.
+    run(&mut flags, "set:faction:miners");
+    run(&mut flags, "add:faction:1");
+    assert_eq!(
+        flags.var("faction"),
+        Some(&Value::Str("miners".to_string()))
+    );
+    assert!(flags.add_var("faction", &Value::Int(1)).is_err());
+}
+
+#[test]
+fn overflow_is_an_error() {
+    let mut flags = GameFlags::default();
+    flags.set_var("credits", Value::Int(i64::MAX));
+    let error = flags.add_var("credits", &Value::Int(1)).unwrap_err();
+    assert!(error.contains("overflows"), "{error}");
+    run(&mut flags, "add:credits:1");
+    assert_eq!(flags.var("credits"), Some(&Value::Int(i64::MAX)));
+
+    flags.set_var("debt", Value::Int(0));
+    run(&mut flags, &format!("sub:debt:{}", i64::MIN));
+    assert_eq!(flags.var("debt"), Some(&Value::Int(0)));
+    flags.set_var("debt", Value::Int(i64::MIN + 1));
+    run(&mut flags, "sub:debt:1");
+    assert_eq!(flags.var("debt"), Some(&Value::Int(i64::MIN)));
+    run(&mut flags, "sub:debt:1");
+    assert_eq!(flags.var("debt"), Some(&Value::Int(i64::MIN)));
+}
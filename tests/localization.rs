@@ -0,0 +1,77 @@
+use bevy::input::InputPlugin;
+use bevy::prelude::*;
+use star_explorer_lib::GameActions;
+use star_explorer_lib::dialogue_actions::ActionRegistry;
+use star_explorer_lib::dialogue_lint::load_dialogue;
+use star_explorer_lib::input_actions::GameActionsPlugin;
+use star_explorer_lib::localization::{
+    FALLBACK_LOCALE, LOCALE_DIR, LOCALES, Localization, LocalizationPlugin, dialogue_keys,
+};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+// Other locales may be partial, since missing keys fall back to English.
+#[test]
+fn every_dialogue_key_is_in_english() {
+    let strings = Localization::load(Path::new(LOCALE_DIR)).unwrap();
+    let mut missing = vec![];
+    for entry in fs::read_dir("assets/dialogue").unwrap() {
+        let path = entry.unwrap().path();
+        let dialogue = load_dialogue(&path, &ActionRegistry::default()).unwrap();
+        for (node, key) in dialogue_keys(&dialogue) {
+            if !strings.contains(FALLBACK_LOCALE, key) {
+                missing.push(format!("{}: {node}: {key}", path.display()));
+            }
+        }
+    }
+    assert!(
+        missing.is_empty(),
+        "missing strings:\n{}",
+        missing.join("\n")
+    );
+}
+
+#[test]
+fn resolves_keys_and_plain_text() {
+    let mut strings = Localization::load(Path::new(LOCALE_DIR)).unwrap();
+    assert!(!strings.set_locale("xx"));
+    assert_eq!(strings.locale(), "en");
+    assert_eq!(strings.get("@earth.goodbye.choice1"), "Close channel.");
+    assert_eq!(strings.get("@no.such.key"), "@no.such.key");
+    assert_eq!(strings.get("Plain text"), "Plain text");
+    assert_eq!(strings.get("@@handle"), "@handle");
+}
+
+#[test]
+fn missing_keys_fall_back_to_english() {
+    let mut strings = Localization::load(Path::new(LOCALE_DIR)).unwrap();
+    assert_eq!(strings.locales(), LOCALES);
+    assert!(strings.set_locale("fr"));
+    assert_eq!(strings.get("@earth.start.choice4"), "Je file. Salut !");
+    assert!(!strings.contains("fr", "earth.start.choice1"));
+    assert_eq!(strings.get("@earth.start.choice1"), "Yes, its delivered!");
+    assert_eq!(strings.get("@no.such.key"), "@no.such.key");
+}
+
+#[test]
+fn tables_load_as_assets() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default(), InputPlugin));
+    app.add_plugins((
+        GameActionsPlugin::<GameActions>::default(),
+        LocalizationPlugin,
+    ));
+    for _ in 0..500 {
+        app.update();
+        if app.world().resource::<Localization>().locales() == LOCALES {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    let mut strings = app.world_mut().resource_mut::<Localization>();
+    assert_eq!(strings.locales(), LOCALES);
+    assert_eq!(strings.get("@earth.speaker"), "Earth Dispatch");
+    strings.set_locale("fr");
+    assert_eq!(strings.get("@earth.speaker"), "Régulation Terre");
+}
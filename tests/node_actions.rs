@@ -0,0 +1,135 @@
+use star_explorer_lib::dialogue_runner::{DialogueRunner, PlaythroughScript};
+use star_explorer_lib::localization::Localization;
+use star_explorer_lib::story_system::{Dialogue, GameFlags};
+use std::collections::HashMap;
+
+const DOCK: &str = r#"{
+    "entry": "hangar",
+    "nodes": [
+        {
+            "id": "hangar",
+            "texts": [{ "text": "Welcome aboard." }],
+            "choices": [
+                { "text": "Collect the bounty.", "next": "bounty" },
+                { "text": "Read the notice board.", "next": "notices" }
+            ],
+            "on_enter": ["add:visits:1"],
+            "on_exit": ["set_flag:left_hangar"]
+        },
+        {
+            "id": "bounty",
+            "texts": [{ "text": "Here's your pay." }],
+            "choices": [{ "text": "Back.", "next": "hangar" }],
+            "on_enter": ["add:credits:500"],
+            "once": true
+        },
+        {
+            "id": "notices",
+            "texts": [{ "text": "Nothing new." }],
+            "choices": [{ "text": "Back.", "next": "hangar" }]
+        }
+    ]
+}"#;
+
+fn value(runner: &DialogueRunner, name: &str) -> String {
+    runner.flags.value(name).to_string()
+}
+
+#[test]
+fn on_enter_runs_once_per_visit() {
+    let dialogue: Dialogue = serde_json::from_str(DOCK).unwrap();
+    let mut runner = DialogueRunner::start(&dialogue, GameFlags::default());
+    assert_eq!(value(&runner, "visits"), "1");
+    assert!(!runner.flags.is_set("left_hangar"));
+
+    runner.choose(1).unwrap();
+    assert!(runner.flags.is_set("left_hangar"));
+    runner.choose(0).unwrap();
+    assert_eq!(value(&runner, "visits"), "2");
+}
+
+#[test]
+fn once_nodes_only_apply_the_first_time() {
+    let dialogue: Dialogue = serde_json::from_str(DOCK).unwrap();
+    let mut runner = DialogueRunner::start(&dialogue, GameFlags::default());
+    for _ in 0..3 {
+        runner.choose(0).unwrap();
+        assert_eq!(runner.text().as_deref(), Some("Here's your pay."));
+        runner.choose(0).unwrap();
+    }
+    assert_eq!(value(&runner, "credits"), "500");
+    assert_eq!(value(&runner, "visits"), "4");
+}
+
+#[test]
+fn runners_dont_keep_flag_changes() {
+    let dialogue: Dialogue = serde_json::from_str(DOCK).unwrap();
+    let mut runner = DialogueRunner::start(&dialogue, GameFlags::default());
+    runner.choose(0).unwrap();
+    assert!(runner.flags.take_changes().is_empty());
+}
+
+const DEPOT: &str = r#"{
+    "entry": "greet",
+    "nodes": [
+        {
+            "id": "greet",
+            "texts": [{ "text": "Need work?" }],
+            "choices": [
+                { "text": "Sure.", "next": "end", "actions": ["resume:job_status"] },
+                { "text": "No.", "next": "end" }
+            ]
+        },
+        {
+            "id": "job_status",
+            "texts": [{ "text": "Back already?" }],
+            "choices": [{ "text": "Bye.", "next": "end" }]
+        }
+    ]
+}"#;
+
+#[test]
+fn resume_sets_where_the_next_hail_starts() {
+    let dialogue: Dialogue = serde_json::from_str(DEPOT).unwrap();
+    let mut runner = DialogueRunner::start(&dialogue, GameFlags::default());
+    assert_eq!(runner.resume, None);
+    runner.choose(0).unwrap();
+    assert!(runner.is_finished());
+    assert_eq!(runner.resume.as_deref(), Some("job_status"));
+    assert!(runner.pending.is_empty());
+
+    let runner = DialogueRunner::resume_seeded(&dialogue, runner.flags, 0, runner.resume);
+    assert_eq!(runner.node_id(), "job_status");
+    assert_eq!(runner.text().as_deref(), Some("Back already?"));
+}
+
+#[test]
+fn scripts_resume_when_re_hailing() {
+    let dialogue: Dialogue = serde_json::from_str(DEPOT).unwrap();
+    let dialogues = HashMap::from([
+        ("depot".to_string(), dialogue.clone()),
+        ("refinery".to_string(), dialogue),
+    ]);
+    let script: PlaythroughScript = serde_json::from_str(
+        r#"{
+            "steps": [
+                { "start": "depot" },
+                { "expect_node": "greet" },
+                { "choose": 0 },
+                { "start": "refinery" },
+                { "expect_node": "greet" },
+                { "start": "depot" },
+                { "expect_node": "job_status" },
+                { "expect_text": "Back already?" }
+            ]
+        }"#,
+    )
+    .unwrap();
+    script
+        .run(
+            &dialogues,
+            &Localization::default(),
+            &mut GameFlags::default(),
+        )
+        .unwrap_or_else(|e| panic!("{e}"));
+}
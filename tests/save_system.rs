@@ -0,0 +1,254 @@
+use bevy::prelude::*;
+use star_explorer_lib::background_stars::BackgroundStarConfig;
+use star_explorer_lib::game_clock::GameClock;
+use star_explorer_lib::save_system::{
+    MIGRATIONS, MissingNode, SAVE_VERSION, SaveData, SaveError, forget_missing_nodes, migrate,
+    missing_nodes,
+};
+use star_explorer_lib::space_position::SpacePosition;
+use star_explorer_lib::story_system::{
+    ActiveDialogue, ChoiceSelected, Dialogue, DialogueEnded, DialogueStarted, GameFlags,
+    NodeEntered,
+};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+fn save_path(name: &str) -> PathBuf {
+    std::env::temp_dir()
+        .join(format!("star_explorer_tests_{}", std::process::id()))
+        .join(name)
+}
+
+fn active_dialogue() -> ActiveDialogue {
+    ActiveDialogue::from_world(&mut World::new())
+}
+
+#[test]
+fn saves_round_trip() {
+    let (earth, mars) = (Entity::from_raw(1), Entity::from_raw(2));
+    let mut flags = GameFlags::default();
+    flags.set("has_job");
+    let mut dialogue = active_dialogue();
+    dialogue.node_id.insert(earth, "job".to_string());
+    dialogue.resume.insert(mars, "delivery".to_string());
+    dialogue
+        .visited
+        .insert(mars, HashSet::from(["start".to_string()]));
+    let config = BackgroundStarConfig {
+        speed: 12.5,
+        direction: 1.25,
+        ..default()
+    };
+    let mut clock = GameClock::default();
+    clock.restore(900);
+    let bodies = HashMap::from([(earth, "Earth".to_string()), (mars, "Mars".to_string())]);
+    let data = SaveData::capture(
+        &flags,
+        &dialogue,
+        &bodies,
+        &SpacePosition(Vec2::new(30.0, -40.0)),
+        &config,
+        &clock,
+    );
+    let path = save_path("round_trip.json");
+    data.write(&path).unwrap();
+    let data = SaveData::read(&path).unwrap();
+    assert_eq!(data.version, SAVE_VERSION);
+
+    // Entities change between runs, so progress goes back to the bodies by name.
+    let (new_earth, new_mars) = (Entity::from_raw(7), Entity::from_raw(8));
+    let bodies = HashMap::from([
+        ("Earth".to_string(), new_earth),
+        ("Mars".to_string(), new_mars),
+    ]);
+    let mut flags = GameFlags::default();
+    let mut dialogue = active_dialogue();
+    dialogue.node_id.insert(earth, "stale".to_string());
+    let mut position = SpacePosition(Vec2::ZERO);
+    let mut config = BackgroundStarConfig::default();
+    let mut clock = GameClock::default();
+    let mut world = World::new();
+    data.apply(
+        &mut flags,
+        &mut dialogue,
+        &bodies,
+        (&mut position, &mut config),
+        &mut clock,
+        &mut world.commands(),
+    );
+    assert!(flags.is_set("has_job"));
+    assert_eq!(
+        dialogue.node_id,
+        HashMap::from([(new_earth, "job".to_string())])
+    );
+    assert_eq!(dialogue.resume[&new_mars], "delivery");
+    assert!(dialogue.visited[&new_mars].contains("start"));
+    assert_eq!(position.0, Vec2::new(30.0, -40.0));
+    assert_eq!((config.speed, config.direction), (12.5, 1.25));
+    assert_eq!(clock.ticks, 900);
+}
+
+#[test]
+fn loading_ends_the_conversation_and_resumes_the_saved_one() {
+    let dialogue: Dialogue = serde_json::from_str(
+        r#"{
+            "entry": "start",
+            "nodes": [
+                {
+                    "id": "start",
+                    "texts": [{ "text": "Hello." }],
+                    "choices": [{ "text": "Job?", "next": "job" }]
+                },
+                {
+                    "id": "job",
+                    "texts": [{ "text": "Take this to Mars." }],
+                    "choices": [{ "text": "Bye.", "next": "end" }],
+                    "on_exit": ["set_flag:left_job"]
+                }
+            ]
+        }"#,
+    )
+    .unwrap();
+    let mut world = World::new();
+    world.init_resource::<Events<DialogueStarted>>();
+    world.init_resource::<Events<NodeEntered>>();
+    world.init_resource::<Events<ChoiceSelected>>();
+    world.init_resource::<Events<DialogueEnded>>();
+    let handle = Handle::<Dialogue>::default();
+    let (earth, mars) = (world.spawn_empty().id(), world.spawn_empty().id());
+    let mut flags = GameFlags::default();
+    let mut active = active_dialogue();
+    active.set_active(&handle, &dialogue, earth, &mut flags, &mut world.commands());
+    active.choose(0, &mut flags, &mut world.commands());
+    let bodies = HashMap::from([(earth, "Earth".to_string())]);
+    let data = SaveData::capture(
+        &flags,
+        &active,
+        &bodies,
+        &SpacePosition(Vec2::ZERO),
+        &BackgroundStarConfig::default(),
+        &GameClock::default(),
+    );
+    active.end(&mut flags, &mut world.commands());
+
+    // Load while talking to Mars.
+    active.set_active(&handle, &dialogue, mars, &mut flags, &mut world.commands());
+    world.flush();
+    world.resource_mut::<Events<DialogueEnded>>().clear();
+    let bodies = HashMap::from([("Earth".to_string(), earth)]);
+    data.apply(
+        &mut flags,
+        &mut active,
+        &bodies,
+        (
+            &mut SpacePosition(Vec2::ZERO),
+            &mut BackgroundStarConfig::default(),
+        ),
+        &mut GameClock::default(),
+        &mut world.commands(),
+    );
+    world.flush();
+    let ended = world
+        .resource_mut::<Events<DialogueEnded>>()
+        .drain()
+        .map(|ended| (ended.speaker, ended.node))
+        .collect::<Vec<_>>();
+    assert_eq!(ended, [(mars, "start".to_string())]);
+    assert_eq!(active.entity, None);
+    assert!(!flags.is_set("left_job"));
+
+    // Earth's conversation was at `job` when the game was saved.
+    active.set_active(&handle, &dialogue, earth, &mut flags, &mut world.commands());
+    assert_eq!(active.node_id(), "job");
+}
+
+#[test]
+fn unreadable_versions_are_rejected() {
+    let mut too_new = serde_json::json!({ "version": SAVE_VERSION + 1 });
+    assert!(matches!(
+        migrate(&mut too_new, MIGRATIONS),
+        Err(SaveError::TooNew(v)) if v == SAVE_VERSION + 1
+    ));
+    for mut save in [
+        serde_json::json!({ "flags": {} }),
+        serde_json::json!({ "version": 0 }),
+        serde_json::json!({ "version": "4" }),
+    ] {
+        assert!(matches!(
+            migrate(&mut save, MIGRATIONS),
+            Err(SaveError::MissingVersion)
+        ));
+    }
+}
+
+#[test]
+fn removed_nodes_are_reported() {
+    let dialogue: Dialogue = serde_json::from_str(
+        r#"{
+            "entry": "start",
+            "nodes": [{ "id": "start", "texts": [{ "text": "Hello." }], "choices": [] }]
+        }"#,
+    )
+    .unwrap();
+    let earth = Entity::from_raw(1);
+    let mut active = active_dialogue();
+    active.node_id.insert(earth, "start".to_string());
+    active.resume.insert(earth, "job".to_string());
+    let data = SaveData::capture(
+        &GameFlags::default(),
+        &active,
+        &HashMap::from([(earth, "Earth".to_string())]),
+        &SpacePosition(Vec2::ZERO),
+        &BackgroundStarConfig::default(),
+        &GameClock::default(),
+    );
+    let dialogues = HashMap::from([("Earth".to_string(), &dialogue)]);
+    let missing = [MissingNode {
+        body: "Earth".to_string(),
+        node: "job".to_string(),
+    }];
+    assert_eq!(missing_nodes(&data.dialogues, &dialogues), missing);
+    // Bodies without a loaded dialogue aren't checked.
+    assert!(missing_nodes(&data.dialogues, &HashMap::new()).is_empty());
+
+    // Whatever still exists is kept.
+    let mut progress = data.dialogues.clone();
+    assert_eq!(forget_missing_nodes(&mut progress, &dialogues), missing);
+    assert_eq!(progress["Earth"].node.as_deref(), Some("start"));
+    assert_eq!(progress["Earth"].resume, None);
+}
+
+#[test]
+fn old_saves_migrate() {
+    for version in 1..SAVE_VERSION {
+        let path = Path::new("tests/saves").join(format!("v{version}.json"));
+        let data = SaveData::read(&path).unwrap_or_else(|e| panic!("v{version}: {e}"));
+        assert_eq!(data.version, SAVE_VERSION);
+        assert!(data.flags.is_set("has_job"));
+        assert_eq!(data.flags.value("credits").to_string(), "150");
+        let player_name = if version == 1 { "Captain" } else { "Ripley" };
+        assert_eq!(data.flags.value("player_name").to_string(), player_name);
+        assert_eq!(data.dialogues["Earth"].node.as_deref(), Some("job"));
+        assert_eq!(data.ship.position, [120.0, -80.0]);
+        assert_eq!(data.clock.ticks, if version < 3 { 0 } else { 3600 });
+        assert_eq!(data.system, None);
+        assert!(data.other_systems.is_empty());
+    }
+}
+
+#[test]
+fn failed_migrations_stop_at_the_last_good_version() {
+    fn ok(_: &mut serde_json::Value) -> Result<(), String> {
+        Ok(())
+    }
+    fn fail(_: &mut serde_json::Value) -> Result<(), String> {
+        Err("broken".to_string())
+    }
+    let mut save = serde_json::json!({ "version": 1 });
+    let error = migrate(&mut save, &[ok, fail, ok]).unwrap_err();
+    assert!(
+        matches!(&error, SaveError::Migration { version: 2, message } if message == "broken"),
+        "{error}"
+    );
+    assert_eq!(save["version"], 2);
+}
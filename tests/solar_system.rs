@@ -0,0 +1,236 @@
+use bevy::math::Vec2;
+use bevy::prelude::*;
+use star_explorer_lib::game_clock::GameClock;
+use star_explorer_lib::solar_system::{
+    OrbitalBody, SolarBody, SolarBodyDescriptor, SolarSystem, SolarSystemPlugin, solve_kepler,
+};
+use star_explorer_lib::story_system::{Dialogue, DialogueHandle};
+use std::f32::consts::TAU;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn default_system_loads() {
+    let json = fs::read_to_string("assets/systems/sol.system.json").unwrap();
+    let sun: SolarBodyDescriptor = serde_json::from_str(&json).unwrap();
+    let names = sun
+        .children
+        .iter()
+        .map(|c| c.name.as_str())
+        .collect::<Vec<_>>();
+    assert!(
+        names.contains(&"Earth") && names.contains(&"Mars"),
+        "{names:?}"
+    );
+}
+
+#[test]
+fn errors_name_the_body_and_where_it_is() {
+    let json = r#"{
+        "name": "Sun",
+        "size": 300.0,
+        "children": [
+            { "name": "Earth", "size": 40.0 },
+            { "name": "Mars", "size": 30.0, "children": [{ "name": "Phobos" }] }
+        ]
+    }"#;
+    let error = serde_json::from_str::<SolarBodyDescriptor>(json).unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .starts_with("body `Phobos` at $.children[1].children[0]: missing field `size`"),
+        "{error}"
+    );
+}
+
+#[test]
+fn names_must_be_unique() {
+    let json = r#"{
+        "name": "Sun",
+        "size": 300.0,
+        "children": [
+            { "name": "Earth", "size": 40.0, "children": [{ "name": "Moon", "size": 10.0 }] },
+            { "name": "Mars", "size": 30.0, "children": [{ "name": "Moon", "size": 5.0 }] }
+        ]
+    }"#;
+    let error = serde_json::from_str::<SolarBodyDescriptor>(json).unwrap_err();
+    assert!(
+        error.to_string().starts_with(
+            "body `Moon` at $.children[1].children[0]: the name is already used by the body at $.children[0].children[0]"
+        ),
+        "{error}"
+    );
+}
+
+fn orbit(json: &str) -> OrbitalBody {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn circular_orbits_are_unchanged() {
+    let circle = orbit(r#"{ "distance": 100.0, "period": 8.0, "start": 0.5 }"#);
+    for time in [0.0, 1.0, 2.5, 7.9] {
+        let angle = time as f32 * TAU / 8.0 + 0.5;
+        let expected = Vec2::new(angle.cos(), angle.sin()) * 100.0;
+        assert!(circle.position(100.0, time).distance(expected) < 1e-3);
+    }
+}
+
+#[test]
+fn ellipses_follow_keplers_laws() {
+    let comet = orbit(
+        r#"{ "distance": 0.0, "period": 10.0, "start": 0.0, "eccentricity": 0.9, "periapsis": 1.0 }"#,
+    );
+    let periapsis = comet.position(100.0, 0.0);
+    let apoapsis = comet.position(100.0, 5.0);
+    assert!((periapsis.length() - 10.0).abs() < 1e-2, "{periapsis}");
+    assert!((apoapsis.length() - 190.0).abs() < 1e-2, "{apoapsis}");
+    assert!((periapsis.to_angle() - 1.0).abs() < 1e-3);
+    assert!(comet.position(100.0, 10.0).distance(periapsis) < 1e-2);
+    // Fast near the parent, slow far from it.
+    let near = comet.position(100.0, 0.1).distance(periapsis);
+    let far = comet.position(100.0, 5.1).distance(apoapsis);
+    assert!(near > far * 10.0, "{near} {far}");
+
+    for e in [0.0, 0.3, 0.7, 0.99] {
+        for m in [0.1, 1.0, 3.0, 6.0] {
+            let anomaly = solve_kepler(m, e);
+            assert!(
+                (anomaly - e * anomaly.sin() - m).abs() < 1e-4,
+                "e {e} m {m}"
+            );
+        }
+    }
+}
+
+#[test]
+fn retrograde_orbits_go_clockwise() {
+    let prograde = orbit(r#"{ "distance": 50.0, "period": 4.0, "start": 0.0 }"#);
+    let retrograde = OrbitalBody {
+        retrograde: true,
+        ..prograde
+    };
+    assert!(prograde.position(50.0, 0.5).y > 0.0);
+    assert!(retrograde.position(50.0, 0.5).y < 0.0);
+}
+
+#[test]
+fn unbound_orbits_are_rejected() {
+    let json = r#"{ "name": "Sun", "size": 300.0, "children": [
+        { "name": "Oumuamua", "size": 1.0, "orbit": { "distance": 10.0, "start": 0.0, "eccentricity": 1.2 } }
+    ] }"#;
+    let error = serde_json::from_str::<SolarBodyDescriptor>(json).unwrap_err();
+    assert!(error.to_string().contains("eccentricity"), "{error}");
+}
+
+fn body(world: &mut World, name: &str) -> Option<Entity> {
+    world
+        .query::<(Entity, &SolarBody)>()
+        .iter(world)
+        .find(|(_, body)| body.name == name)
+        .map(|(entity, _)| entity)
+}
+
+fn headless_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default()));
+    app.init_asset::<Image>()
+        .init_asset::<Mesh>()
+        .init_asset::<ColorMaterial>()
+        .init_asset::<Dialogue>();
+    app.add_plugins(SolarSystemPlugin);
+    app.init_resource::<GameClock>();
+    app
+}
+
+/// Spawns `system` as the current one.
+fn spawn(app: &mut App, system: SolarBodyDescriptor) -> Handle<SolarBodyDescriptor> {
+    let handle = app
+        .world_mut()
+        .resource_mut::<Assets<SolarBodyDescriptor>>()
+        .add(system);
+    app.world_mut().resource_mut::<SolarSystem>().handle = handle.clone();
+    app.update();
+    app.update();
+    handle
+}
+
+#[test]
+fn bodies_get_the_dialogue_they_declare() {
+    let mut app = headless_app();
+    let system = serde_json::from_str(
+        r#"{
+            "name": "Test Star",
+            "size": 300.0,
+            "children": [
+                { "name": "Talker", "size": 20.0, "dialogue": "dialogue/earth.json" },
+                { "name": "Mute", "size": 20.0 }
+            ]
+        }"#,
+    )
+    .unwrap();
+    spawn(&mut app, system);
+
+    let world = app.world_mut();
+    let (talker, mute) = (body(world, "Talker").unwrap(), body(world, "Mute").unwrap());
+    let DialogueHandle(handle) = world.get::<DialogueHandle>(talker).unwrap();
+    let path = world.resource::<AssetServer>().get_path(handle).unwrap();
+    assert_eq!(path.path(), Path::new("dialogue/earth.json"));
+    assert!(world.get::<DialogueHandle>(mute).is_none());
+}
+
+#[test]
+fn reloads_keep_colours_and_drop_removed_bodies() {
+    let mut app = headless_app();
+    let system: SolarBodyDescriptor = serde_json::from_str(
+        r#"{
+            "name": "Test Star",
+            "size": 300.0,
+            "tint": { "Srgba": { "red": 1.0, "green": 0.9, "blue": 0.8, "alpha": 1.0 } },
+            "children": [
+                { "name": "Plain", "size": 20.0, "orbit": { "distance": 500.0, "start": 0.0 } },
+                {
+                    "name": "Gone",
+                    "size": 30.0,
+                    "orbit": { "distance": 900.0, "start": 0.0 },
+                    "children": [{ "name": "Moonlet", "size": 5.0, "orbit": { "distance": 60.0, "start": 0.0 } }]
+                }
+            ]
+        }"#,
+    )
+    .unwrap();
+    let handle = spawn(&mut app, system);
+
+    let world = app.world_mut();
+    let (star, plain) = (
+        body(world, "Test Star").unwrap(),
+        body(world, "Plain").unwrap(),
+    );
+    let (gone, moonlet) = (
+        body(world, "Gone").unwrap(),
+        body(world, "Moonlet").unwrap(),
+    );
+    let material = world
+        .get::<MeshMaterial2d<ColorMaterial>>(plain)
+        .unwrap()
+        .0
+        .id();
+
+    world
+        .resource_mut::<Assets<SolarBodyDescriptor>>()
+        .get_mut(&handle)
+        .unwrap()
+        .children
+        .retain(|child| child.name != "Gone");
+    app.update();
+    app.update();
+
+    let world = app.world_mut();
+    assert_eq!(body(world, "Plain"), Some(plain));
+    let reloaded = world.get::<MeshMaterial2d<ColorMaterial>>(plain).unwrap();
+    assert_eq!(reloaded.0.id(), material);
+    assert!(world.get_entity(gone).is_err());
+    assert!(world.get_entity(moonlet).is_err());
+    let children = world.get::<Children>(star).unwrap();
+    assert_eq!(children.iter().copied().collect::<Vec<_>>(), [plain]);
+}
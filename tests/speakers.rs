@@ -0,0 +1,75 @@
+use star_explorer_lib::dialogue_lint::lint_speakers;
+use star_explorer_lib::dialogue_runner::DialogueRunner;
+use star_explorer_lib::story_system::{Dialogue, GameFlags};
+use std::path::{Path, PathBuf};
+
+const OUTPOST: &str = r##"{
+    "entry": "gate",
+    "speaker": { "name": "Gate Control", "accent": "#e0704a" },
+    "speakers": {
+        "chief": { "name": "Chief Ruiz", "portrait": "portraits/ruiz.png" }
+    },
+    "nodes": [
+        {
+            "id": "gate",
+            "texts": [{ "text": "{speaker} here. State your business." }],
+            "choices": [{ "text": "Delivery.", "next": "chief" }]
+        },
+        {
+            "id": "chief",
+            "texts": [{ "text": "This is {speaker}. Bring it in.", "speaker": "chief" }],
+            "choices": [{ "text": "Bye.", "next": "end" }]
+        }
+    ]
+}"##;
+
+#[test]
+fn texts_can_change_speaker() {
+    let dialogue: Dialogue = serde_json::from_str(OUTPOST).unwrap();
+    let mut runner = DialogueRunner::start(&dialogue, GameFlags::default()).with_speaker("Mars");
+    assert_eq!(
+        runner.text().as_deref(),
+        Some("Gate Control here. State your business.")
+    );
+    assert_eq!(runner.speaker().unwrap().accent.as_deref(), Some("#e0704a"));
+
+    runner.choose(0).unwrap();
+    assert_eq!(
+        runner.text().as_deref(),
+        Some("This is Chief Ruiz. Bring it in.")
+    );
+    assert_eq!(
+        runner.speaker().unwrap().portrait.as_deref(),
+        Some("portraits/ruiz.png")
+    );
+}
+
+#[test]
+fn unknown_speakers_are_rejected_on_load() {
+    let json = OUTPOST.replace(r#""speaker": "chief""#, r#""speaker": "chef""#);
+    let error = serde_json::from_str::<Dialogue>(&json).unwrap_err();
+    assert!(
+        error.to_string().contains("unknown speaker `chef`"),
+        "{error}"
+    );
+}
+
+#[test]
+fn lint_checks_accents_and_files() {
+    let json = OUTPOST.replace("#e0704a", "reddish");
+    let dialogues = vec![(
+        PathBuf::from("outpost.json"),
+        serde_json::from_str(&json).unwrap(),
+    )];
+    let messages = lint_speakers(&dialogues, Path::new("assets"))
+        .into_iter()
+        .map(|issue| issue.message)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        [
+            "the default speaker has an invalid accent `reddish`",
+            "speaker `chief`'s portrait `portraits/ruiz.png` doesn't exist",
+        ]
+    );
+}
@@ -0,0 +1,46 @@
+use star_explorer_lib::solar_system::SolarBodyDescriptor;
+use star_explorer_lib::system_generator::generate_system;
+use std::collections::HashSet;
+
+fn bodies(body: &SolarBodyDescriptor) -> Vec<&SolarBodyDescriptor> {
+    let mut all = vec![body];
+    for child in body.children.iter() {
+        all.extend(bodies(child));
+    }
+    all
+}
+
+#[test]
+fn same_seed_same_system() {
+    let json = |seed| serde_json::to_string(&generate_system(seed)).unwrap();
+    assert_eq!(json(7), json(7));
+    assert_ne!(json(7), json(8));
+}
+
+#[test]
+fn generated_systems_load_back() {
+    for seed in 0..50 {
+        let system = generate_system(seed);
+        let json = serde_json::to_string_pretty(&system).unwrap();
+        let loaded: SolarBodyDescriptor = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string_pretty(&loaded).unwrap(), json);
+
+        let all = bodies(&system);
+        assert!(all.len() > 1, "seed {seed} has no planets");
+        let names = all.iter().map(|b| b.name.as_str()).collect::<HashSet<_>>();
+        assert_eq!(names.len(), all.len(), "seed {seed} reuses a name");
+        for body in all.iter() {
+            assert!(body.size > 0.0, "{}", body.name);
+        }
+    }
+}
+
+/// Seeded systems in the galaxy file are named after what their seed generates, so
+/// the output for a seed must never change. Regenerate the snapshot with
+/// `star_explorer-generate 7` only if breaking every seed is intended.
+#[test]
+fn seeds_keep_their_systems() {
+    let snapshot = std::fs::read_to_string("tests/systems/seed_7.system.json").unwrap();
+    let json = serde_json::to_string_pretty(&generate_system(7)).unwrap();
+    assert_eq!(json.trim(), snapshot.trim());
+}
@@ -0,0 +1,110 @@
+use bevy::prelude::*;
+use star_explorer_lib::communication_system::{
+    CHARS_PER_SECOND, CONTINUE_PROMPT, LINE_CHARS, RevealedLine, TextReveal, VISIBLE_LINES,
+    paginate, wrap,
+};
+
+fn line(node: &str) -> RevealedLine {
+    (Some(Entity::from_raw(1)), node.to_string(), None)
+}
+
+/// Seconds the typewriter takes to show `chars` characters.
+fn seconds(chars: usize) -> f32 {
+    chars as f32 / CHARS_PER_SECOND
+}
+
+#[test]
+fn lines_wrap_between_words() {
+    let text = "word ".repeat(40);
+    let lines = wrap(&text);
+    assert!(lines.len() > 1);
+    for line in lines.iter() {
+        assert!(line.chars().count() <= LINE_CHARS, "{line:?}");
+        assert!(!line.starts_with(' ') && !line.ends_with(' '), "{line:?}");
+    }
+    assert_eq!(lines.join(" "), text.trim());
+    // A word longer than the panel gets a line to itself rather than being split.
+    let long = "x".repeat(LINE_CHARS + 10);
+    assert_eq!(wrap(&format!("a {long} b")), ["a", long.as_str(), "b"]);
+    assert_eq!(wrap(""), [""]);
+}
+
+#[test]
+fn blank_lines_split_pages() {
+    assert_eq!(
+        paginate("Hello.\nStill page one.\n\nPage two.\n\n\n\nPage three."),
+        [
+            vec!["Hello.", "Still page one."],
+            vec!["Page two."],
+            vec!["Page three."],
+        ]
+    );
+    assert!(paginate("").is_empty());
+}
+
+#[test]
+fn text_appears_a_few_characters_at_a_time() {
+    let mut reveal = TextReveal::default();
+    reveal.set(line("start"), "Hello there.");
+    assert_eq!(reveal.visible_text(), "");
+    reveal.tick(seconds(5));
+    assert_eq!(reveal.visible_text(), "Hello");
+    assert!(!reveal.is_done());
+    reveal.tick(seconds(100));
+    assert_eq!(reveal.visible_text(), "Hello there.");
+    assert!(reveal.is_done());
+
+    // The same line with new text, such as a changing ship speed, keeps its place.
+    reveal.set(line("start"), "Hello there, captain.");
+    assert_eq!(reveal.visible_text(), "Hello there,");
+    assert!(!reveal.is_done());
+    // A new line starts over.
+    reveal.set(line("next"), "Goodbye.");
+    assert_eq!(reveal.line(), &line("next"));
+    assert_eq!(reveal.visible_text(), "");
+}
+
+#[test]
+fn skipping_finishes_the_page_then_turns_it() {
+    let mut reveal = TextReveal::default();
+    reveal.set(line("start"), "First page.\n\nSecond page.");
+    reveal.tick(seconds(3));
+    assert!(reveal.skip());
+    assert_eq!(
+        reveal.visible_text(),
+        format!("First page.\n{CONTINUE_PROMPT}")
+    );
+    assert!(!reveal.is_done());
+
+    assert!(reveal.skip());
+    assert_eq!(reveal.visible_text(), "");
+    assert!(reveal.skip());
+    assert_eq!(reveal.visible_text(), "Second page.");
+    assert!(reveal.is_done());
+
+    // With everything shown, the press is left for taking a choice.
+    assert!(!reveal.skip());
+    assert_eq!(reveal.visible_text(), "Second page.");
+}
+
+#[test]
+fn long_pages_scroll() {
+    let message = (0..VISIBLE_LINES + 3)
+        .map(|n| format!("Line {n}."))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut reveal = TextReveal::default();
+    reveal.set(line("start"), &message);
+    reveal.tick(seconds(message.len()));
+    // The reveal follows the last line.
+    let shown = reveal.visible_text();
+    assert_eq!(shown.lines().count(), VISIBLE_LINES);
+    assert_eq!(shown.lines().last(), Some("Line 9."));
+
+    reveal.scroll_by(-100);
+    assert_eq!(reveal.visible_text().lines().next(), Some("Line 0."));
+    reveal.scroll_by(1);
+    assert_eq!(reveal.visible_text().lines().next(), Some("Line 1."));
+    reveal.scroll_by(100);
+    assert_eq!(reveal.visible_text().lines().last(), Some("Line 9."));
+}
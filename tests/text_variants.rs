@@ -0,0 +1,46 @@
+use star_explorer_lib::dialogue_runner::DialogueRunner;
+use star_explorer_lib::story_system::{Dialogue, GameFlags};
+
+const BARKS: &str = r#"{
+    "entry": "hail",
+    "nodes": [
+        {
+            "id": "hail",
+            "pick": "random",
+            "texts": [
+                { "condition": "angry", "text": "Go away." },
+                { "condition": null, "text": "Hello there.", "weight": 3 },
+                { "condition": null, "text": "Nice day for it." },
+                { "condition": null, "text": "Back again?" },
+                { "condition": null, "text": "Never shown.", "weight": 0 }
+            ],
+            "choices": [{ "text": "Hail again.", "next": "hail" }]
+        }
+    ]
+}"#;
+
+fn texts(seed: u64, count: usize) -> Vec<String> {
+    let dialogue: Dialogue = serde_json::from_str(BARKS).unwrap();
+    let mut runner = DialogueRunner::start_seeded(&dialogue, GameFlags::default(), seed);
+    let mut texts = vec![runner.text().unwrap()];
+    for _ in 1..count {
+        runner.choose(0).unwrap();
+        texts.push(runner.text().unwrap());
+    }
+    texts
+}
+
+#[test]
+fn random_texts_never_repeat_straight_away() {
+    let texts = texts(7, 200);
+    assert!(texts.windows(2).all(|pair| pair[0] != pair[1]));
+    for text in ["Hello there.", "Nice day for it.", "Back again?"] {
+        assert!(texts.iter().any(|t| t == text), "{text} never shown");
+    }
+    assert!(!texts.iter().any(|t| t == "Go away." || t == "Never shown."));
+}
+
+#[test]
+fn the_same_seed_shows_the_same_texts() {
+    assert_eq!(texts(42, 50), texts(42, 50));
+}